@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use anyhow::{anyhow, bail, Context, Result};
+
+// overrides where the assets are looked for, after --assets
+const ASSETS_ENV: &str = "SIRSQUARE_ASSETS";
+const MANIFEST_FILE: &str = "manifest.txt";
+
+static ASSETS: OnceLock<Assets> = OnceLock::new();
+
+/// What assets/manifest.txt lists. One entry per line, blank lines and lines starting with # are skipped:
+///
+/// texture <name> <tile width> <tile height>   image <name>.png cut into tiles
+/// map <name> <music>                          <name>.tmx drawn with the texture of the same name
+/// music <name> <file>                         file names may contain spaces
+/// sound <name> <file>
+#[derive(Default)]
+pub struct Manifest {
+    pub textures: HashMap<String, (u32, u32)>,
+    // music played on each map
+    pub maps: HashMap<String, String>,
+    pub music: HashMap<String, String>,
+    pub sounds: HashMap<String, String>,
+}
+
+impl Manifest {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut manifest = Manifest::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.splitn(3, char::is_whitespace);
+            let kind = words.next().unwrap_or_default();
+            let name = words.next().ok_or_else(|| anyhow!("Line {} has no name", number + 1))?.to_string();
+            let rest = words.next().unwrap_or_default().trim();
+
+            match kind {
+                "texture" => {
+                    let sizes: Vec<u32> = rest.split_whitespace().filter_map(|size| size.parse().ok()).collect();
+                    let [tile_width, tile_height] = sizes[..] else {
+                        bail!("Line {}: texture {} needs a tile width and height", number + 1, name);
+                    };
+                    manifest.textures.insert(name, (tile_width, tile_height));
+                },
+                "map" => {
+                    manifest.maps.insert(name, rest.to_string());
+                },
+                "music" => {
+                    manifest.music.insert(name, rest.to_string());
+                },
+                "sound" => {
+                    manifest.sounds.insert(name, rest.to_string());
+                },
+                _ => bail!("Line {}: unknown asset kind {}", number + 1, kind),
+            }
+        }
+
+        Ok(manifest)
+    }
+}
+
+pub struct Assets {
+    pub root: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Assets {
+    pub fn load(root: PathBuf) -> Result<Self> {
+        let manifest_path = root.join(MANIFEST_FILE);
+        let text = fs::read_to_string(&manifest_path).with_context(|| format!("Failed to read {:?}", manifest_path))?;
+        let manifest = Manifest::parse(&text)?;
+
+        Ok(Self { root, manifest })
+    }
+
+    pub fn path(&self, file: &str) -> PathBuf {
+        self.root.join(file)
+    }
+
+    pub fn tile_size(&self, texture: &str) -> Result<(u32, u32)> {
+        self.manifest.textures.get(texture).copied()
+            .ok_or_else(|| anyhow!("Texture {} is not in the asset manifest", texture))
+    }
+
+    /// Width and height that fit every texture in the manifest, read from the png headers
+    pub fn largest_texture(&self) -> Result<(u32, u32)> {
+        let mut largest = (1, 1);
+        for name in self.manifest.textures.keys() {
+            let path = self.path(&format!("{}.png", name));
+            let (width, height) = image::image_dimensions(&path).with_context(|| format!("Failed to read the size of {:?}", path))?;
+            largest = (largest.0.max(width), largest.1.max(height));
+        }
+
+        Ok(largest)
+    }
+
+    pub fn map_path(&self, map: &str) -> Result<PathBuf> {
+        if !self.manifest.maps.contains_key(map) {
+            bail!("Map {} is not in the asset manifest", map);
+        }
+        Ok(self.path(&format!("{}.tmx", map)))
+    }
+
+    pub fn music_path(&self, name: &str) -> Option<PathBuf> {
+        self.manifest.music.get(name).map(|file| self.path(file))
+    }
+
+    pub fn map_music(&self, map: &str) -> Option<PathBuf> {
+        self.manifest.maps.get(map).and_then(|music| self.music_path(music))
+    }
+
+    /// Sounds missing from the manifest are taken as a file name, as older scripts use
+    pub fn sound_path(&self, name: &str) -> PathBuf {
+        self.path(self.manifest.sounds.get(name).map_or(name, String::as_str))
+    }
+}
+
+fn has_manifest(dir: &Path) -> bool {
+    dir.join(MANIFEST_FILE).is_file()
+}
+
+/// Finds the asset folder from --assets, then SIRSQUARE_ASSETS, then an assets folder
+/// next to the executable or in any folder above it, which covers running from target/
+pub fn find_root(flag: Option<&Path>) -> Result<PathBuf> {
+    if let Some(dir) = flag {
+        return Ok(dir.to_path_buf());
+    }
+
+    if let Some(dir) = env::var_os(ASSETS_ENV) {
+        return Ok(PathBuf::from(dir));
+    }
+
+    let exe = env::current_exe().context("Failed to find the executable")?;
+    exe.ancestors()
+        .skip(1)
+        .map(|dir| dir.join("assets"))
+        .find(|dir| has_manifest(dir))
+        .ok_or_else(|| anyhow!("No assets folder found near {:?}, pass --assets or set {}", exe, ASSETS_ENV))
+}
+
+/// Sets up the assets for the rest of the run, later calls are ignored
+pub fn init(root: PathBuf) -> Result<()> {
+    if ASSETS.get().is_some() {
+        return Ok(());
+    }
+
+    let assets = Assets::load(root)?;
+    println!("Assets: {:?}", assets.root);
+    let _ = ASSETS.set(assets);
+    Ok(())
+}
+
+pub fn get() -> &'static Assets {
+    ASSETS.get().expect("assets::init has to be called before loading anything")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_blank_and_comment_lines() {
+        let manifest = Manifest::parse("\n# textures\n   \n  # indented comment\ntexture landing 16 16\n").unwrap();
+
+        assert_eq!(manifest.textures.len(), 1);
+        assert_eq!(manifest.textures["landing"], (16, 16));
+    }
+
+    #[test]
+    fn keeps_spaces_in_file_names() {
+        let manifest = Manifest::parse("music gym Pokemon Gym.mp3\nsound door Door Open.wav\nmap gym gym\n").unwrap();
+
+        assert_eq!(manifest.music["gym"], "Pokemon Gym.mp3");
+        assert_eq!(manifest.sounds["door"], "Door Open.wav");
+        assert_eq!(manifest.maps["gym"], "gym");
+    }
+
+    #[test]
+    fn texture_needs_both_sizes() {
+        let error = Manifest::parse("texture landing 16 16\ntexture menu 16\n").err().unwrap();
+        assert!(error.to_string().contains("Line 2"), "{}", error);
+
+        assert!(Manifest::parse("texture menu").is_err());
+        assert!(Manifest::parse("texture menu 16 wide").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_kinds() {
+        let error = Manifest::parse("font white_font 7 11").err().unwrap();
+        assert!(error.to_string().contains("unknown asset kind font"), "{}", error);
+    }
+}
@@ -5,18 +5,22 @@ use std::sync::Arc;
 
 pub struct AudioPlayer {
     sink: Option<Arc<Sink>>,
-    stream_handle: OutputStreamHandle,
+    // None without an audio device, nothing is played then
+    stream_handle: Option<OutputStreamHandle>,
     looping: bool,       // Flag to control looping
     current_track: Option<String>,  // Track currently playing
+    // sound effects play on a sink of their own so the music carries on underneath
+    effect: Option<Sink>,
 }
 
 impl AudioPlayer {
-    pub fn new(stream_handle: OutputStreamHandle) -> Self {
+    pub fn new(stream_handle: Option<OutputStreamHandle>) -> Self {
         AudioPlayer {
             sink: None,
             stream_handle,
             looping: false,  // Default to no looping
             current_track: None,  // No track playing initially
+            effect: None,
         }
     }
 
@@ -26,6 +30,10 @@ impl AudioPlayer {
     }
 
     pub fn play(&mut self, path: &str) {
+        let Some(stream_handle) = &self.stream_handle else {
+            return;
+        };
+
         // Check if the same track is already playing
         if let Some(current_track) = &self.current_track {
             if current_track == path && self.is_playing() {
@@ -40,11 +48,17 @@ impl AudioPlayer {
         }
 
         // Load the new audio file
-        let file = File::open(path).unwrap();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                println!("Failed to open {}: {:?}", path, e);
+                return;
+            },
+        };
         let source = Decoder::new(BufReader::new(file)).unwrap();
 
         // Create a new sink and configure looping if needed
-        let sink = Arc::new(Sink::try_new(&self.stream_handle).unwrap());
+        let sink = Arc::new(Sink::try_new(stream_handle).unwrap());
 
         if self.looping {
             // If looping is enabled, play the source in a loop
@@ -59,6 +73,32 @@ impl AudioPlayer {
         self.current_track = Some(path.to_string());
     }
 
+    /// Plays a sound effect once over the music, cutting off the previous effect
+    pub fn play_sound(&mut self, path: &str) {
+        let Some(stream_handle) = &self.stream_handle else {
+            return;
+        };
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                println!("Failed to open {}: {:?}", path, e);
+                return;
+            },
+        };
+        let source = match Decoder::new(BufReader::new(file)) {
+            Ok(source) => source,
+            Err(e) => {
+                println!("Failed to decode {}: {:?}", path, e);
+                return;
+            },
+        };
+
+        let sink = Sink::try_new(stream_handle).unwrap();
+        sink.append(source);
+        self.effect = Some(sink);
+    }
+
     pub fn pause(&self) {
         if let Some(sink) = &self.sink {
             sink.pause();
@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+use image::{Delay, Frame, RgbaImage};
+use image::codecs::gif::{GifEncoder, Repeat};
+use winit::dpi::PhysicalSize;
+use winit::event::{ElementState, KeyEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use crate::options::Options;
+use crate::renderer::Renderer;
+use crate::window::config::{WIDTH, HEIGHT};
+
+// frames drawn sooner than this after the last recorded one are skipped
+const RECORD_FRAME_INTERVAL: Duration = Duration::from_millis(33);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RecordFormat {
+    Gif,
+    // one png per frame in a folder of its own
+    PngSequence,
+}
+
+/// Screenshots and recordings for bug reports. F12 saves a screenshot at 240 by 160 and shift F12
+/// one at the window's scale. F11 starts recording and, pressed again, saves the last few seconds
+pub struct Capture {
+    dir: PathBuf,
+    record_duration: Duration,
+    record_format: RecordFormat,
+    shift: bool,
+    // waiting for the next frame, true at the window's scale
+    screenshot: Option<bool>,
+    recording: bool,
+    frames: VecDeque<(Instant, RgbaImage)>,
+    last_recorded: Option<Instant>,
+    // captures still on their way back from the gpu, by the id the renderer gave them
+    pending: VecDeque<(u64, Pending)>,
+}
+
+#[derive(Clone, Copy)]
+enum Pending {
+    Screenshot,
+    // when the frame was drawn, it can take a few frames to arrive
+    Recording(Instant),
+}
+
+// milliseconds since the epoch, enough to keep file names apart
+fn timestamp() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_millis())
+}
+
+impl Capture {
+    pub fn new(options: &Options) -> Self {
+        Self {
+            dir: options.capture_dir.clone(),
+            record_duration: Duration::from_secs(options.capture_seconds),
+            record_format: options.capture_format,
+            shift: false,
+            screenshot: None,
+            recording: false,
+            frames: VecDeque::new(),
+            last_recorded: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn input(&mut self, event: &KeyEvent) {
+        let PhysicalKey::Code(keycode) = event.physical_key else {
+            return;
+        };
+        let pressed = event.state == ElementState::Pressed;
+
+        match keycode {
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => self.shift = pressed,
+            KeyCode::F12 if pressed && !event.repeat => self.screenshot = Some(self.shift),
+            KeyCode::F11 if pressed && !event.repeat => self.toggle_recording(),
+            _ => {},
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        self.recording = !self.recording;
+        if self.recording {
+            println!("Recording, press F11 again to save the last {} seconds", self.record_duration.as_secs());
+            return;
+        }
+
+        let frames: Vec<(Instant, RgbaImage)> = self.frames.drain(..).collect();
+        self.last_recorded = None;
+        if frames.is_empty() {
+            println!("Recording stopped before any frames were captured");
+            return;
+        }
+
+        // encoding takes a while, the game carries on meanwhile
+        let (dir, format) = (self.dir.clone(), self.record_format);
+        std::thread::spawn(move || {
+            match save_recording(&dir, format, &frames) {
+                Ok(path) => println!("Saved recording of {} frames to {:?}", frames.len(), path),
+                Err(e) => println!("Failed to save recording: {:?}", e),
+            }
+        });
+    }
+
+    /// Called before the game draws, asks the renderer to keep a copy of the frame if needed.
+    /// A capture the renderer has no room for is tried again next frame
+    pub fn before_draw(&mut self, renderer: &mut Renderer) {
+        let now = Instant::now();
+        let native = PhysicalSize::new(WIDTH, HEIGHT);
+
+        if let Some(window_scale) = self.screenshot {
+            if let Some(id) = renderer.capture_next_frame(if window_scale { renderer.content_size() } else { native }) {
+                self.screenshot = None;
+                self.pending.push_back((id, Pending::Screenshot));
+            }
+        } else if self.recording && self.last_recorded.map_or(true, |last| now - last >= RECORD_FRAME_INTERVAL) {
+            if let Some(id) = renderer.capture_next_frame(native) {
+                self.last_recorded = Some(now);
+                self.pending.push_back((id, Pending::Recording(now)));
+            }
+        }
+    }
+
+    /// Called after the game draws, picks up whichever earlier captures the gpu has finished
+    pub fn after_draw(&mut self, renderer: &mut Renderer) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        for (id, frame) in renderer.take_captures() {
+            // captures that never got drawn don't come back, skip past them
+            while self.pending.front().map_or(false, |(pending_id, _)| *pending_id < id) {
+                self.pending.pop_front();
+            }
+            let Some(&(pending_id, pending)) = self.pending.front() else {
+                continue;
+            };
+            if pending_id != id {
+                continue;
+            }
+            self.pending.pop_front();
+
+            let frame = match frame {
+                Ok(frame) => frame,
+                Err(e) => {
+                    println!("Failed to capture the frame: {:?}", e);
+                    continue;
+                },
+            };
+
+            match pending {
+                Pending::Screenshot => {
+                    let path = self.dir.join(format!("screenshot-{}.png", timestamp()));
+                    let saved = fs::create_dir_all(&self.dir).map_err(anyhow::Error::from)
+                        .and_then(|_| frame.save(&path).map_err(anyhow::Error::from));
+                    match saved {
+                        Ok(()) => println!("Saved screenshot to {:?}", path),
+                        Err(e) => println!("Failed to save screenshot: {:?}", e),
+                    }
+                },
+                // frames arriving after the recording was saved are dropped
+                Pending::Recording(_) if !self.recording => {},
+                Pending::Recording(time) => {
+                    self.frames.push_back((time, frame));
+                    while self.frames.front().map_or(false, |(first, _)| time - *first > self.record_duration) {
+                        self.frames.pop_front();
+                    }
+                },
+            }
+        }
+    }
+}
+
+fn save_recording(dir: &Path, format: RecordFormat, frames: &[(Instant, RgbaImage)]) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    match format {
+        RecordFormat::Gif => {
+            let path = dir.join(format!("recording-{}.gif", timestamp()));
+            let mut encoder = GifEncoder::new(File::create(&path)?);
+            encoder.set_repeat(Repeat::Infinite)?;
+
+            for (i, (time, image)) in frames.iter().enumerate() {
+                // each frame shows until the next one was captured
+                let duration = frames.get(i + 1).map_or(RECORD_FRAME_INTERVAL, |(next, _)| *next - *time);
+                let delay = Delay::from_saturating_duration(duration);
+                encoder.encode_frame(Frame::from_parts(image.clone(), 0, 0, delay))?;
+            }
+
+            Ok(path)
+        },
+        RecordFormat::PngSequence => {
+            let path = dir.join(format!("recording-{}", timestamp()));
+            fs::create_dir_all(&path)?;
+
+            for (i, (_, image)) in frames.iter().enumerate() {
+                image.save(path.join(format!("frame-{:04}.png", i)))?;
+            }
+
+            Ok(path)
+        },
+    }
+}
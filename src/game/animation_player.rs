@@ -191,6 +191,14 @@ impl AnimationPlayer {
         }
     }
 
+    // moves the current animation without advancing its frames
+    pub fn set_position(&mut self, position: Vector3<f32>) {
+        self.animations
+            .get_mut(&self.current_animation)
+            .unwrap()
+            .update(position, Duration::ZERO);
+    }
+
     pub fn get_instances(&self) -> &Vec<Instance> {
         self.animations
             .get(&self.current_animation)
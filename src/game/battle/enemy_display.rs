@@ -10,6 +10,7 @@ pub struct EnemyDisplay {
     level: Font,
     name: Font,
     health_bar: Sprite,
+    offset: (f32, f32),
 }
 
 impl Display for EnemyDisplay {
@@ -21,7 +22,8 @@ impl Display for EnemyDisplay {
             x if x > 0.25 => 1,
             _ => 2,
         };
-        self.health_bar = renderer.create_sprite(54.0, 33.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
+        let (ox, oy) = self.offset;
+        self.health_bar = renderer.create_sprite(ox + 54.0, oy + 33.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
     }
 
     fn draw(&self, instances: &mut Vec<Instance>) {
@@ -33,13 +35,14 @@ impl Display for EnemyDisplay {
 }
 
 impl EnemyDisplay {
-    pub fn new(pokemon: &Pokemon, renderer: &mut Renderer) -> Self {
+    pub fn new(pokemon: &Pokemon, offset: (f32, f32), renderer: &mut Renderer) -> Self {
+        let (ox, oy) = offset;
 
-        let background = renderer.create_sprite(0.0, 16.0, 0, 10, 7, 2, "battle", 1.0, 1.0).expect("");
+        let background = renderer.create_sprite(ox, oy + 16.0, 0, 10, 7, 2, "battle", 1.0, 1.0).expect("");
 
-        let name = Font::new(21.0, 19.0, &pokemon.name.to_uppercase(), true, "black_font", renderer);
+        let name = Font::new(ox + 21.0, oy + 19.0, &pokemon.display_name().to_uppercase(), true, "black_font", renderer);
         let level = "Lv".to_string() + &pokemon.level.to_string();
-        let level = Font::new(95.0, 19.0, &level, false, "black_font",  renderer);
+        let level = Font::new(ox + 95.0, oy + 19.0, &level, false, "black_font",  renderer);
 
         let percent_hp = pokemon.current_hp as f32 / pokemon.stats.hp as f32;
         let y_offset = match percent_hp {
@@ -47,20 +50,22 @@ impl EnemyDisplay {
             x if x > 0.25 => 1,
             _ => 2,
         };
-        let health_bar = renderer.create_sprite(54.0, 33.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
+        let health_bar = renderer.create_sprite(ox + 54.0, oy + 33.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
 
         Self {
             background,
             level,
             name,
             health_bar,
+            offset,
         }
     }
 
     pub fn swap(&mut self, pokemon: &Pokemon, renderer: &mut Renderer) {
-        self.name = Font::new(21.0, 19.0, &pokemon.name.to_uppercase(), true, "black_font", renderer);
+        let (ox, oy) = self.offset;
+        self.name = Font::new(ox + 21.0, oy + 19.0, &pokemon.display_name().to_uppercase(), true, "black_font", renderer);
         let level = "Lv".to_string() + &pokemon.level.to_string();
-        self.level = Font::new(95.0, 19.0, &level, false, "black_font",  renderer);
+        self.level = Font::new(ox + 95.0, oy + 19.0, &level, false, "black_font",  renderer);
 
         let percent_hp = pokemon.current_hp as f32 / pokemon.stats.hp as f32;
         let y_offset = match percent_hp {
@@ -68,7 +73,8 @@ impl EnemyDisplay {
             x if x > 0.25 => 1,
             _ => 2,
         };
-        self.health_bar = renderer.create_sprite(54.0, 33.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
+        let (ox, oy) = self.offset;
+        self.health_bar = renderer.create_sprite(ox + 54.0, oy + 33.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
     }
 
 
@@ -0,0 +1,68 @@
+use crate::game::items::{HeldEffect, Item};
+use crate::game::moves::Move;
+use crate::game::pokemon::Pokemon;
+use crate::renderer::Renderer;
+use super::{Battle, Display};
+
+// Held items hook into the turn at three points:
+//   damage     - type boosting items raise the damage of matching moves by a fifth
+//   after hit  - berries are eaten once the holder drops to half hp
+//   turn end   - leftovers restore a little hp to every active holder
+
+fn held_effect(pokemon: &Pokemon) -> Option<HeldEffect> {
+    pokemon.held_item.as_deref().and_then(|name| Item::new(name).held)
+}
+
+pub fn boosts_move(attacker: &Pokemon, _move: &Move) -> bool {
+    matches!(held_effect(attacker), Some(HeldEffect::TypeBoost(boosted)) if boosted == _move.move_type)
+}
+
+/// Returns true if a berry was eaten
+pub fn check_berry(pokemon: &mut Pokemon) -> bool {
+    let Some(HeldEffect::Berry(amount)) = held_effect(pokemon) else {
+        return false;
+    };
+
+    if pokemon.current_hp == 0 || pokemon.current_hp > pokemon.stats.hp / 2 {
+        return false;
+    }
+
+    pokemon.current_hp = (pokemon.current_hp + amount).min(pokemon.stats.hp);
+    let berry = pokemon.held_item.take().unwrap_or_default();
+    println!("{} ate its {}.", pokemon.display_name(), berry);
+    true
+}
+
+fn end_of_turn_heal(pokemon: &mut Pokemon) -> bool {
+    let Some(HeldEffect::Leftovers) = held_effect(pokemon) else {
+        return false;
+    };
+
+    if pokemon.current_hp == 0 || pokemon.current_hp == pokemon.stats.hp {
+        return false;
+    }
+
+    pokemon.current_hp = (pokemon.current_hp + (pokemon.stats.hp / 16).max(1)).min(pokemon.stats.hp);
+    println!("{} restored a little hp using its Leftovers.", pokemon.display_name());
+    true
+}
+
+impl Battle {
+    pub(super) fn end_turn(&mut self, player_party: &mut Vec<Pokemon>, renderer: &mut Renderer) {
+        for (slot, active) in self.player_active.iter().enumerate() {
+            if let Some(index) = *active {
+                if end_of_turn_heal(&mut player_party[index]) {
+                    self.player_displays[slot].update_health_bar(&player_party[index], renderer);
+                }
+            }
+        }
+
+        for (slot, active) in self.enemy_active.iter().enumerate() {
+            if let Some(index) = *active {
+                if end_of_turn_heal(&mut self.enemy_party[index]) {
+                    self.enemy_displays[slot].update_health_bar(&self.enemy_party[index], renderer);
+                }
+            }
+        }
+    }
+}
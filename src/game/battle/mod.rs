@@ -1,21 +1,34 @@
 mod ui;
 mod player_display;
 mod enemy_display;
+pub mod replay;
+mod stages;
+mod held_items;
 
 use winit::keyboard::KeyCode;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::time::Duration;
 use crate::game::input_manager::InputManager;
 use crate::renderer::Renderer;
+use crate::renderer::draw_list::{DrawList, Layer, Space, Sort};
 use crate::game::party::Party;
 use crate::renderer::sprite::Sprite;
 use crate::game::pokemon::Pokemon;
-use ui::{UI, MenuState, FightState, UIMessage};
+use ui::{UI, MenuState, UIMessage};
 use player_display::PlayerDisplay;
 use enemy_display::EnemyDisplay;
-use super::moves::Move;
-use rand::Rng;
+use super::moves::{Move, Target, MoveEffect, Stat, AttackType};
+use crate::game::pokemon::Status;
+use stages::StatStages;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 use crate::renderer::instance::Instance;
+use replay::{Replay, RecordedAction};
+
+const PLAYBACK_TURN_DELAY: Duration = Duration::from_millis(1500);
+// draw order of the pokemon sprites, the player's back sprites overlap the enemy's
+const ENEMY_SPRITE_Z: f32 = 0.0;
+const PLAYER_SPRITE_Z: f32 = 1.0;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BattleType {
@@ -23,14 +36,32 @@ pub enum BattleType {
     Trainer,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BattleFormat {
+    Single,
+    Double,
+    // double battle against two trainers, each enemy slot is fed from its own trainer's party
+    Tag,
+}
+
+/// How a battle finished, handed back by Battle::update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BattleOutcome {
+    Won,
+    Lost,
+    // stopped without a winner, the player ran or made a catch or one side was blown away
+    Ended,
+    // a replay ran out of recorded actions before the battle was decided
+    ReplayEnded,
+}
+
 pub enum BattleState {
     PlayerTurn,
     EnemyTurn,
     HandleMoves,
     MoveText,
-    PlayerFaint,
+    Faint,
     PlayerForceSwap,
-    EnemyFaint,
 }
 
 pub trait Display {
@@ -40,25 +71,43 @@ pub trait Display {
 
 pub struct Battle {
     pub battle_type: BattleType,
+    pub format: BattleFormat,
     battle_state: BattleState,
     ui: UI,
-    player_pokemon_index: usize,
-    enemy_pokemon_index: usize,
-    player_display: PlayerDisplay,
-    enemy_display: EnemyDisplay,
+    // party index of the pokemon in each active slot, None once a slot can't be refilled
+    player_active: Vec<Option<usize>>,
+    enemy_active: Vec<Option<usize>>,
+    player_stages: Vec<StatStages>,
+    enemy_stages: Vec<StatStages>,
+    player_displays: Vec<PlayerDisplay>,
+    enemy_displays: Vec<EnemyDisplay>,
     background: Sprite,
-    player_action: Option<Action>,
-    enemy_action: Option<Action>,
+    actions: Vec<Action>,
+    choosing_slot: usize,
+    forced_swaps: VecDeque<usize>,
     enemy_party: Vec<Pokemon>,
+    enemy_owners: Vec<usize>,
     escape_attempts: u32,
+    pub seed: u64,
+    rng: StdRng,
+    pub replay: Replay,
+    playback: Option<VecDeque<RecordedAction>>,
+    playback_timer: Duration,
+    // species ids for the pokedex
+    pub seen: Vec<u32>,
+    pub caught: Option<u32>,
 }
 
 pub struct Action {
     action_type: ActionType,
-    priority: u32,
     is_player: bool,
+    // active slot of the user
+    slot: usize,
+    // active slot on the opposing side, used by single target moves
+    target: usize,
 }
 
+#[derive(Clone)]
 pub enum ActionType {
     Move {
         _move: Move,
@@ -73,100 +122,245 @@ pub enum ActionType {
 }
 
 impl Battle {
-    pub fn new(battle_type: BattleType, player_pokemon: &mut Vec<Pokemon>, enemy_pokemon: Vec<Pokemon>, renderer: &mut Renderer) -> Self {
-        println!("You encountered a level {} {}", enemy_pokemon[0].level, enemy_pokemon[0].name);
+    pub fn new(battle_type: BattleType, format: BattleFormat, player_pokemon: &mut Vec<Pokemon>, enemy_pokemon: Vec<Pokemon>, enemy_owners: Vec<usize>, seed: u64, renderer: &mut Renderer) -> Self {
+        println!("You encountered a level {} {}", enemy_pokemon[0].level, enemy_pokemon[0].display_name());
+        println!("Battle seed: {}", seed);
+        let seen = enemy_pokemon.iter().map(|pokemon| pokemon.id).collect();
         let background = renderer.create_sprite(0.0, 0.0, 0, 0, 15, 7, "battle", 1.0, 1.0).expect("");
 
-        //first non fainted pokemon
-        let first_index = player_pokemon.iter().position(|p| p.current_hp > 0).unwrap();
-        let first_pokemon = &player_pokemon[first_index];
+        let slot_count = match format {
+            BattleFormat::Single => 1,
+            BattleFormat::Double | BattleFormat::Tag => 2,
+        };
 
-        let ui = UI::new(first_pokemon, renderer);
-        let player_display = PlayerDisplay::new(first_pokemon, renderer);
-        let enemy_display = EnemyDisplay::new(&enemy_pokemon[0], renderer);
+        //first non fainted pokemon for each slot
+        let mut player_active = Vec::new();
+        for _ in 0..slot_count {
+            let next = player_pokemon.iter().enumerate()
+                .position(|(i, p)| p.current_hp > 0 && !player_active.contains(&Some(i)));
+            player_active.push(next);
+        }
+
+        let mut enemy_active = Vec::new();
+        for slot in 0..slot_count {
+            let owner = if format == BattleFormat::Tag { slot } else { 0 };
+            let next = enemy_pokemon.iter().enumerate()
+                .position(|(i, p)| p.current_hp > 0 && enemy_owners[i] == owner && !enemy_active.contains(&Some(i)));
+            enemy_active.push(next);
+        }
+
+        let first_index = player_active[0].unwrap();
+        let ui = UI::new(&player_pokemon[first_index], renderer);
+
+        let mut player_displays = Vec::new();
+        for (slot, active) in player_active.iter().enumerate() {
+            let index = active.unwrap_or(first_index);
+            player_displays.push(PlayerDisplay::new(&player_pokemon[index], Self::player_display_offset(format, slot), renderer));
+        }
+
+        let mut enemy_displays = Vec::new();
+        for (slot, active) in enemy_active.iter().enumerate() {
+            let index = active.unwrap_or(0);
+            enemy_displays.push(EnemyDisplay::new(&enemy_pokemon[index], Self::enemy_display_offset(format, slot), renderer));
+        }
 
         let battle_state = BattleState::PlayerTurn;
 
-        Battle {
+        let replay = Replay::new(battle_type, format, seed, player_pokemon, &enemy_pokemon, &enemy_owners);
+
+        let mut battle = Battle {
             battle_type,
+            format,
             battle_state,
             ui,
-            player_pokemon_index: first_index,
-            enemy_pokemon_index: 0,
-            player_display,
-            enemy_display,
+            player_stages: vec![StatStages::default(); slot_count],
+            enemy_stages: vec![StatStages::default(); slot_count],
+            player_active,
+            enemy_active,
+            player_displays,
+            enemy_displays,
             background,
-            player_action: None,
-            enemy_action: None,
+            actions: Vec::new(),
+            choosing_slot: 0,
+            forced_swaps: VecDeque::new(),
             enemy_party: enemy_pokemon,
+            enemy_owners,
             escape_attempts: 0,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            replay,
+            playback: None,
+            playback_timer: Duration::new(0, 0),
+            seen,
+            caught: None,
+        };
+
+        battle.update_targets();
+        battle
+    }
+
+    fn player_display_offset(format: BattleFormat, slot: usize) -> (f32, f32) {
+        match (format, slot) {
+            (BattleFormat::Single, _) => (0.0, 0.0),
+            (_, 0) => (0.0, -48.0),
+            _ => (0.0, 0.0),
+        }
+    }
+
+    fn enemy_display_offset(format: BattleFormat, slot: usize) -> (f32, f32) {
+        match (format, slot) {
+            (BattleFormat::Single, _) => (0.0, 0.0),
+            (_, 0) => (0.0, -16.0),
+            _ => (0.0, 16.0),
+        }
+    }
+
+    // horizontal shift applied to battler sprites so two of them fit side by side
+    fn sprite_offset(&self, slot: usize) -> f32 {
+        match (self.format, slot) {
+            (BattleFormat::Single, _) => 0.0,
+            (_, 0) => -24.0,
+            _ => 32.0,
         }
     }
 
+    pub fn play_back(&mut self, actions: Vec<RecordedAction>) {
+        self.playback = Some(actions.into_iter().collect());
+        self.playback_timer = PLAYBACK_TURN_DELAY;
+    }
+
+    fn next_recorded_action(&mut self, is_player: bool) -> Option<RecordedAction> {
+        let playback = self.playback.as_mut()?;
+        let index = playback.iter().position(|action| action.is_player == is_player)?;
+        playback.remove(index)
+    }
 
-    pub fn update(&mut self, player_party: &mut Vec<Pokemon>, input_manager: &mut InputManager, dt: Duration, renderer: &mut Renderer) -> Option<bool> {
+    fn update_targets(&mut self) {
+        let valid_targets = self.enemy_active.iter()
+            .map(|active| active.map_or(false, |i| self.enemy_party[i].current_hp > 0))
+            .collect();
+        self.ui.set_targets(valid_targets);
+    }
+
+    fn enemy_slot_owner(&self, slot: usize) -> usize {
+        if self.format == BattleFormat::Tag { slot } else { 0 }
+    }
+
+    fn start_turn(&mut self, player_party: &Vec<Pokemon>, renderer: &mut Renderer) {
+        self.choosing_slot = self.player_active.iter().position(|active| active.is_some()).unwrap_or(0);
+        if let Some(index) = self.player_active[self.choosing_slot] {
+            self.ui.update_moves(&player_party[index], renderer);
+        }
+        self.update_targets();
+        self.ui.return_to_main();
+        self.battle_state = BattleState::PlayerTurn;
+    }
+
+    pub fn update(&mut self, player_party: &mut Vec<Pokemon>, input_manager: &mut InputManager, dt: Duration, renderer: &mut Renderer) -> Option<BattleOutcome> {
         // Handle encounter updates and input
         renderer.camera.update_camera(cgmath::Vector3::new(0.0, 0.0, 0.0));
 
         match self.battle_state {
             BattleState::PlayerTurn => {
-                if let Some(action) = self.handle_player_turn(player_party, input_manager, dt, renderer) {
-                    self.player_action = Some(action);
-                    self.battle_state = BattleState::EnemyTurn;
+                let action = if self.playback.is_some() {
+                    match self.handle_playback_turn(dt) {
+                        Some(Some(action)) => Some(action),
+                        Some(None) => {
+                            println!("Replay ended.");
+                            return Some(BattleOutcome::ReplayEnded);
+                        },
+                        None => None,
+                    }
+                } else {
+                    self.handle_player_turn(player_party, input_manager, dt, renderer)
+                };
+
+                if let Some(action) = action {
+                    self.replay.record(true, action.slot, action.target, &action.action_type);
+
+                    let ends_choice = matches!(action.action_type, ActionType::Catch { .. } | ActionType::Run);
+                    self.actions.push(action);
+
+                    let next_slot = (self.choosing_slot + 1..self.player_active.len())
+                        .find(|&slot| self.player_active[slot].is_some());
+
+                    match next_slot {
+                        Some(slot) if !ends_choice => {
+                            self.choosing_slot = slot;
+                            let index = self.player_active[slot].unwrap();
+                            self.ui.update_moves(&player_party[index], renderer);
+                            self.ui.return_to_main();
+                        },
+                        _ => {
+                            self.battle_state = BattleState::EnemyTurn;
+                        },
+                    }
                 }
             },
             BattleState::EnemyTurn => {
-                if let Some(action) = self.handle_enemy_turn(input_manager, dt, renderer) {
-                    self.enemy_action = Some(action);
-                    self.battle_state = BattleState::HandleMoves;
+                for slot in 0..self.enemy_active.len() {
+                    if let Some(action) = self.handle_enemy_turn(slot, player_party) {
+                        self.replay.record(false, action.slot, action.target, &action.action_type);
+                        self.actions.push(action);
+                    }
                 }
+                self.battle_state = BattleState::HandleMoves;
             },
             BattleState::HandleMoves => {
-                if self.resolve_moves(player_party, input_manager, dt, renderer) {
-                    return Some(true);
+                if let Some(outcome) = self.resolve_moves(player_party, renderer) {
+                    return Some(outcome);
                 }
+                self.apply_poison(player_party, renderer);
+                self.end_turn(player_party, renderer);
+                self.battle_state = BattleState::Faint;
             },
-            BattleState::PlayerFaint => {
-                // Handle PlayerFaint state
-                // if player has no pokemon they lose
-                if player_party.iter().all(|p| p.current_hp == 0) {
-                    println!("Player has no more pokemon. Player loses.");
-                    return Some(false);
-                }
-
-                //switch to next pokemon
-                self.ui.open_swap_menu(player_party, renderer);
-                self.battle_state = BattleState::PlayerForceSwap;
+            BattleState::Faint => {
+                return self.handle_faints(player_party, renderer);
             },
             BattleState::PlayerForceSwap => {
                 // Handle PlayerForceSwap state
-                if let Some(ui_message) = self.ui.update(player_party, input_manager, dt, renderer) {
+                let ui_message = if self.playback.is_some() {
+                    match self.next_recorded_action(true) {
+                        Some(RecordedAction { action_type: ActionType::Swap { slot }, .. }) => {
+                            self.ui.return_to_main();
+                            Some(UIMessage::Swap { slot })
+                        },
+                        _ => {
+                            println!("Replay ended.");
+                            return Some(BattleOutcome::ReplayEnded);
+                        },
+                    }
+                } else {
+                    self.ui.update(player_party, input_manager, dt, renderer)
+                };
+
+                if let Some(ui_message) = ui_message {
                     match ui_message {
                         UIMessage::Swap { slot } => {
-                            self.player_pokemon_index = slot as usize;
-                            self.player_display.swap(&player_party[self.player_pokemon_index], renderer);
-                            self.ui.update_moves(&player_party[self.player_pokemon_index], renderer);
-                            self.battle_state = BattleState::PlayerTurn;
+                            let battle_slot = *self.forced_swaps.front().unwrap();
+
+                            if self.player_active.contains(&Some(slot as usize)) {
+                                println!("{} is already in battle.", player_party[slot as usize].display_name());
+                                self.ui.open_swap_menu(player_party, renderer);
+                                return None;
+                            }
+
+                            self.forced_swaps.pop_front();
+                            self.replay.record(true, battle_slot, 0, &ActionType::Swap { slot });
+                            self.player_active[battle_slot] = Some(slot as usize);
+                            self.player_stages[battle_slot] = StatStages::default();
+                            self.player_displays[battle_slot].swap(&player_party[slot as usize], renderer);
+
+                            if self.forced_swaps.is_empty() {
+                                self.start_turn(player_party, renderer);
+                            } else {
+                                self.ui.open_swap_menu(player_party, renderer);
+                            }
                         },
                         _ => {},
                     }
                 }
             },
-            BattleState::EnemyFaint => {
-                // Handle EnemyFaint state
-                //gain exp
-                let exp = self.enemy_party[self.enemy_pokemon_index].experience_yield;
-                player_party[self.player_pokemon_index].gain_experience(exp);
-
-                if self.enemy_party.iter().all(|p| p.current_hp == 0) {
-                    println!("All enemy pokemon fainted. Player wins.");
-                    return Some(true);
-                }
-
-                self.enemy_pokemon_index += 1;
-                self.enemy_display.swap(&self.enemy_party[self.enemy_pokemon_index], renderer);
-                self.battle_state = BattleState::PlayerTurn;
-            },
             BattleState::MoveText => {
                 // Handle MoveText state
             },
@@ -175,45 +369,153 @@ impl Battle {
         None
     }
 
+    fn handle_faints(&mut self, player_party: &mut Vec<Pokemon>, renderer: &mut Renderer) -> Option<BattleOutcome> {
+        //gain exp for every enemy that fainted this turn
+        for slot in 0..self.enemy_active.len() {
+            if let Some(index) = self.enemy_active[slot] {
+                if self.enemy_party[index].current_hp == 0 {
+                    let exp = self.enemy_party[index].experience_yield;
+                    for active in self.player_active.iter().flatten() {
+                        if player_party[*active].current_hp > 0 {
+                            player_party[*active].gain_experience(exp);
+                        }
+                    }
+                    self.enemy_active[slot] = None;
+                }
+            }
+        }
+
+        if self.enemy_party.iter().all(|p| p.current_hp == 0) {
+            println!("All enemy pokemon fainted. Player wins.");
+            return Some(BattleOutcome::Won);
+        }
+
+        // if player has no pokemon they lose
+        if player_party.iter().all(|p| p.current_hp == 0) {
+            println!("Player has no more pokemon. Player loses.");
+            return Some(BattleOutcome::Lost);
+        }
+
+        //send in the next enemy pokemon
+        for slot in 0..self.enemy_active.len() {
+            if self.enemy_active[slot].is_some() {
+                continue;
+            }
+
+            let owner = self.enemy_slot_owner(slot);
+            let next = (0..self.enemy_party.len()).find(|&i| {
+                self.enemy_party[i].current_hp > 0 && self.enemy_owners[i] == owner && !self.enemy_active.contains(&Some(i))
+            });
+
+            if let Some(index) = next {
+                self.enemy_active[slot] = Some(index);
+                self.enemy_stages[slot] = StatStages::default();
+                self.enemy_displays[slot].swap(&self.enemy_party[index], renderer);
+            }
+        }
+
+        //fainted player pokemon are replaced if there is anyone left on the bench
+        for slot in 0..self.player_active.len() {
+            if let Some(index) = self.player_active[slot] {
+                if player_party[index].current_hp > 0 {
+                    continue;
+                }
+
+                let bench_available = (0..player_party.len()).any(|i| {
+                    player_party[i].current_hp > 0 && !self.player_active.contains(&Some(i))
+                });
+
+                if bench_available {
+                    self.forced_swaps.push_back(slot);
+                } else {
+                    self.player_active[slot] = None;
+                }
+            }
+        }
+
+        if self.forced_swaps.is_empty() {
+            self.start_turn(player_party, renderer);
+        } else {
+            //switch to next pokemon
+            self.ui.open_swap_menu(player_party, renderer);
+            self.battle_state = BattleState::PlayerForceSwap;
+        }
+
+        None
+    }
+
     fn handle_player_turn(&mut self, player_party: &mut Vec<Pokemon>, input_manager: &mut InputManager, dt: Duration, renderer: &mut Renderer) -> Option<Action> {
+        let slot = self.choosing_slot;
+        let index = self.player_active[slot]?;
 
         if let Some(ui_message) = self.ui.update(player_party, input_manager, dt, renderer) {
 
             let action = match ui_message {
-                UIMessage::Move { fight_state } => {
+                UIMessage::Move { fight_state, target } => {
                     //map fight_state to a move
-                    let _move = match fight_state {
-                        FightState::Move1 => player_party[self.player_pokemon_index].moves[0].clone(),
-                        FightState::Move2 => player_party[self.player_pokemon_index].moves[1].clone(),
-                        FightState::Move3 => player_party[self.player_pokemon_index].moves[2].clone(),
-                        FightState::Move4 => player_party[self.player_pokemon_index].moves[3].clone(),
+                    let chosen = &player_party[index].moves[fight_state as usize];
+                    let out_of_pp = player_party[index].moves.iter().all(|a_move| a_move.current_pp == 0);
+
+                    let _move = if out_of_pp {
+                        println!("{} has no moves left.", player_party[index].display_name());
+                        Move::new("Struggle")
+                    } else if chosen.current_pp == 0 {
+                        println!("There's no PP left for {}.", chosen.name);
+                        self.ui.show_message(&["There's no PP left", "for this move."], renderer);
+                        self.ui.return_to_main();
+                        return None;
+                    } else {
+                        chosen.clone()
                     };
 
                     Action {
                         action_type: ActionType::Move { _move },
-                        priority: player_party[self.player_pokemon_index].stats.speed,
                         is_player: true,
+                        slot,
+                        target,
                     }
                 },
-                UIMessage::Catch => {
+                UIMessage::Catch { target } => {
+                    if self.battle_type == BattleType::Trainer {
+                        println!("Trainer pokemon can't be caught.");
+                        self.ui.show_message(&["You cannot catch", "their pokemon."], renderer);
+                        self.ui.return_to_main();
+                        return None;
+                    }
+
                     Action {
                         action_type: ActionType::Catch { ball_bonus: 1.0},
-                        priority: 910,
                         is_player: true,
+                        slot,
+                        target,
                     }
                 },
-                UIMessage::Swap { slot } => {
+                UIMessage::Swap { slot: party_slot } => {
+                    if self.player_active.contains(&Some(party_slot as usize)) {
+                        println!("{} is already in battle.", player_party[party_slot as usize].display_name());
+                        return None;
+                    }
+
                     Action {
-                        action_type: ActionType::Swap { slot },
-                        priority: 900,
+                        action_type: ActionType::Swap { slot: party_slot },
                         is_player: true,
+                        slot,
+                        target: 0,
                     }
                 },
                 UIMessage::Run => {
+                    if self.battle_type == BattleType::Trainer {
+                        println!("There is no running from a trainer battle.");
+                        self.ui.show_message(&["You cannot", "run away."], renderer);
+                        self.ui.return_to_main();
+                        return None;
+                    }
+
                     Action {
                         action_type: ActionType::Run,
-                        priority: 999,
                         is_player: true,
+                        slot,
+                        target: 0,
                     }
                 },
             };
@@ -224,102 +526,304 @@ impl Battle {
         return None;
     }
 
-    fn handle_enemy_turn(&mut self, input_manager: &mut InputManager, dt: Duration, renderer: &mut Renderer) -> Option<Action> {
-        //select a random move from the enemy pokemon
-        //should be from 0 to length of moves
-        let move_index = rand::thread_rng().gen_range(0..self.enemy_party[self.enemy_pokemon_index].moves.len());
-        let _move = self.enemy_party[self.enemy_pokemon_index].moves[move_index].clone();
+    // Returns None while waiting between turns, Some(None) once the recording runs out
+    fn handle_playback_turn(&mut self, dt: Duration) -> Option<Option<Action>> {
+        if self.playback_timer > dt {
+            self.playback_timer -= dt;
+            return None;
+        }
+        self.playback_timer = PLAYBACK_TURN_DELAY;
+
+        let recorded = match self.next_recorded_action(true) {
+            Some(recorded) => recorded,
+            None => return Some(None),
+        };
+
+        Some(Some(Action {
+            action_type: recorded.action_type,
+            is_player: true,
+            slot: recorded.slot,
+            target: recorded.target,
+        }))
+    }
+
+    fn handle_enemy_turn(&mut self, slot: usize, player_party: &Vec<Pokemon>) -> Option<Action> {
+        let index = self.enemy_active[slot]?;
+
+        //select a random move from the enemy pokemon's moves with pp left, struggling once there are none
+        let usable: Vec<&Move> = self.enemy_party[index].moves.iter().filter(|a_move| a_move.current_pp > 0).collect();
+        let _move = match usable.len() {
+            0 => Move::new("Struggle"),
+            n => usable[self.rng.gen_range(0..n)].clone(),
+        };
+
+        //pick one of the player's battlers, only rolled when there is a choice
+        let targets: Vec<usize> = (0..self.player_active.len())
+            .filter(|&s| self.player_active[s].map_or(false, |i| player_party[i].current_hp > 0))
+            .collect();
+        let target = match targets.len() {
+            0 => 0,
+            1 => targets[0],
+            n => targets[self.rng.gen_range(0..n)],
+        };
+
+        //the random draws above still happen during playback to keep the rng in step
+        let (action_type, target) = match self.next_recorded_action(false) {
+            Some(recorded) => (recorded.action_type, recorded.target),
+            None => (ActionType::Move { _move }, target),
+        };
 
         let action = Action {
-            action_type: ActionType::Move { _move },
-            priority: self.enemy_party[self.enemy_pokemon_index].stats.speed,
+            action_type,
             is_player: false,
+            slot,
+            target,
         };
 
         return Some(action);
     }
 
-    fn resolve_moves(&mut self, player_party: &mut Vec<Pokemon>, input_manager: &mut InputManager, dt: Duration, renderer: &mut Renderer) -> bool {
-        let player_action = self.player_action.take();
-        let enemy_action = self.enemy_action.take();
+    fn resolve_moves(&mut self, player_party: &mut Vec<Pokemon>, renderer: &mut Renderer) -> Option<BattleOutcome> {
+        let actions = std::mem::take(&mut self.actions);
+
+        // Determine the order of actions: bracket, then move priority, then speed, then a coin flip
+        let mut ordered: Vec<((u32, i32, u32, u32), Action)> = actions.into_iter()
+            .map(|action| (self.turn_order(&action, player_party), action))
+            .collect();
+        ordered.sort_by(|a, b| b.0.cmp(&a.0));
 
-        if let (Some(player_action), Some(enemy_action)) = (player_action, enemy_action) {
-            // Determine the order of actions based on priority
-            let (first_action, second_action) = if player_action.priority >= enemy_action.priority {
-                (player_action, enemy_action)
-            } else {
-                (enemy_action, player_action)
+        for (_, action) in &ordered {
+            //fainted battlers don't get to act
+            let user = if action.is_player { self.player_active[action.slot] } else { self.enemy_active[action.slot] };
+            let user_fainted = match user {
+                Some(index) if action.is_player => player_party[index].current_hp == 0,
+                Some(index) => self.enemy_party[index].current_hp == 0,
+                None => true,
             };
+            if user_fainted {
+                continue;
+            }
 
-            if self.execute_action(&first_action, player_party, renderer) {
+            if matches!(action.action_type, ActionType::Move { .. }) && self.still_asleep(action, player_party) {
+                continue;
+            }
 
-                if let ActionType::Run = first_action.action_type {
-                    return true;
-                }
+            if let ActionType::Move { _move } = &action.action_type {
+                self.spend_pp(action, &_move.name, player_party);
+            }
 
-                if let ActionType::Catch { .. } = first_action.action_type {
-                    //add pokemon to party
-                    player_party.push(self.enemy_party.remove(self.enemy_pokemon_index));
-                    return true;
+            if let Some(outcome) = self.execute_action(action, player_party, renderer) {
+                if let ActionType::Catch { .. } = action.action_type {
+                    //add pokemon to party, the enemy party keeps its entry so the other indices stay valid
+                    if let Some(index) = self.enemy_active[action.target].take() {
+                        self.caught = Some(self.enemy_party[index].id);
+                        player_party.push(self.enemy_party[index].clone());
+                    }
                 }
 
-                if first_action.is_player {
-                    self.battle_state = BattleState::EnemyFaint;
-                } else {
-                    self.battle_state = BattleState::PlayerFaint;
-                }
+                return Some(outcome);
+            }
 
-                return false;
+            let player_wiped = self.player_active.iter().flatten().all(|&i| player_party[i].current_hp == 0);
+            let enemy_wiped = self.enemy_active.iter().flatten().all(|&i| self.enemy_party[i].current_hp == 0);
+            if player_wiped || enemy_wiped {
+                break;
             }
+        }
 
-            if self.execute_action(&second_action, player_party, renderer) {
-                if second_action.is_player {
-                    self.battle_state = BattleState::EnemyFaint;
-                } else {
-                    self.battle_state = BattleState::PlayerFaint;
+        None
+    }
+
+    // Counts down a sleeping user's turns, it wakes up and moves once they run out
+    fn still_asleep(&mut self, action: &Action, player_party: &mut Vec<Pokemon>) -> bool {
+        let pokemon = if action.is_player {
+            &mut player_party[self.player_active[action.slot].unwrap()]
+        } else {
+            &mut self.enemy_party[self.enemy_active[action.slot].unwrap()]
+        };
+
+        if pokemon.status != Some(Status::Sleep) {
+            return false;
+        }
+
+        if pokemon.sleep_turns == 0 {
+            pokemon.status = None;
+            println!("{} woke up!", pokemon.display_name());
+            return false;
+        }
+
+        pokemon.sleep_turns -= 1;
+        println!("{} is fast asleep.", pokemon.display_name());
+        true
+    }
+
+    // the action holds a copy of the move, the one in the user's move list is the one used up
+    fn spend_pp(&mut self, action: &Action, move_name: &str, player_party: &mut Vec<Pokemon>) {
+        let pokemon = if action.is_player {
+            &mut player_party[self.player_active[action.slot].unwrap()]
+        } else {
+            &mut self.enemy_party[self.enemy_active[action.slot].unwrap()]
+        };
+
+        if let Some(a_move) = pokemon.moves.iter_mut().find(|a_move| a_move.name == move_name) {
+            a_move.current_pp = a_move.current_pp.saturating_sub(1);
+        }
+    }
+
+    // poisoned battlers lose an eighth of their max hp at the end of each turn
+    fn apply_poison(&mut self, player_party: &mut Vec<Pokemon>, renderer: &mut Renderer) {
+        for (slot, active) in self.player_active.iter().enumerate() {
+            if let Some(index) = *active {
+                if poison_damage(&mut player_party[index]) {
+                    self.player_displays[slot].update_health_bar(&player_party[index], renderer);
                 }
-                return false;
             }
+        }
 
-
-            // If the battle continues, reset the state to PlayerTurn
-            self.battle_state = BattleState::PlayerTurn;
-            self.ui.return_to_main();
+        for (slot, active) in self.enemy_active.iter().enumerate() {
+            if let Some(index) = *active {
+                if poison_damage(&mut self.enemy_party[index]) {
+                    self.enemy_displays[slot].update_health_bar(&self.enemy_party[index], renderer);
+                }
+            }
         }
+    }
 
-        false
+    fn turn_order(&mut self, action: &Action, player_party: &Vec<Pokemon>) -> (u32, i32, u32, u32) {
+        let bracket = match action.action_type {
+            ActionType::Run => 3,
+            ActionType::Catch { .. } => 2,
+            ActionType::Swap { .. } => 1,
+            ActionType::Move { .. } => 0,
+        };
+
+        let move_priority = match &action.action_type {
+            ActionType::Move { _move } => _move.priority,
+            _ => 0,
+        };
+
+        let tie_break = self.rng.gen();
+
+        (bracket, move_priority, self.effective_speed(action.is_player, action.slot, player_party), tie_break)
+    }
+
+    fn effective_speed(&self, is_player: bool, slot: usize, player_party: &Vec<Pokemon>) -> u32 {
+        let (pokemon, stages) = if is_player {
+            match self.player_active[slot] {
+                Some(index) => (&player_party[index], &self.player_stages[slot]),
+                None => return 0,
+            }
+        } else {
+            match self.enemy_active[slot] {
+                Some(index) => (&self.enemy_party[index], &self.enemy_stages[slot]),
+                None => return 0,
+            }
+        };
+
+        let speed = stages.apply(Stat::Speed, pokemon.stats.speed);
+
+        if pokemon.status == Some(Status::Paralysis) {
+            speed / 2
+        } else {
+            speed
+        }
     }
 
-    fn execute_action(&mut self, action: &Action, player_party: &mut Vec<Pokemon>, renderer: &mut Renderer) -> bool {
-        let (attacker, defender, defender_display) = if action.is_player {
-            (&player_party[self.player_pokemon_index], &mut self.enemy_party[self.enemy_pokemon_index], &mut self.enemy_display as &mut dyn Display)
+    // Returns the opposing slots hit by a move, retargeting if the chosen slot is down
+    fn move_targets(&self, action: &Action, _move: &Move, player_party: &Vec<Pokemon>) -> Vec<usize> {
+        let alive: Vec<usize> = if action.is_player {
+            (0..self.enemy_active.len())
+                .filter(|&s| self.enemy_active[s].map_or(false, |i| self.enemy_party[i].current_hp > 0))
+                .collect()
         } else {
-            (&self.enemy_party[self.enemy_pokemon_index], &mut player_party[self.player_pokemon_index], &mut self.player_display as &mut dyn Display)
+            (0..self.player_active.len())
+                .filter(|&s| self.player_active[s].map_or(false, |i| player_party[i].current_hp > 0))
+                .collect()
         };
 
+        match _move.target {
+            Target::User => Vec::new(),
+            Target::AllFoes => alive,
+            Target::Single => {
+                if alive.contains(&action.target) {
+                    vec![action.target]
+                } else {
+                    alive.into_iter().take(1).collect()
+                }
+            },
+        }
+    }
+
+    fn execute_action(&mut self, action: &Action, player_party: &mut Vec<Pokemon>, renderer: &mut Renderer) -> Option<BattleOutcome> {
+        let user = if action.is_player { "Player" } else { "Enemy" };
+        let foe = if !action.is_player { "Player" } else { "Enemy" };
+
         match &action.action_type {
             ActionType::Move { _move } => {
+                let targets = self.move_targets(action, _move, player_party);
 
-                if _move.power != 0 {
-                    let damage = ((2 * attacker.level / 5 + 2) * _move.power * attacker.stats.attack / defender.stats.defense) / 50 + 2;
-                    let user = if action.is_player { "Player" } else { "Enemy" };
-                    println!("{}'s {} used {}. Dealt {} damage.", user, attacker.name, _move.name, damage);
-
-                    defender.current_hp = defender.current_hp.saturating_sub(damage);
-                    defender_display.update_health_bar(defender, renderer);
+                let attacker = if action.is_player {
+                    &player_party[self.player_active[action.slot].unwrap()]
                 } else {
-                    let user = if action.is_player { "Player" } else { "Enemy" };
-                    println!("{}'s {} used {}.", user, attacker.name, _move.name);
+                    &self.enemy_party[self.enemy_active[action.slot].unwrap()]
+                };
+                let attacker_stages = if action.is_player { self.player_stages[action.slot] } else { self.enemy_stages[action.slot] };
+                let (attacker_name, attacker_level) = (attacker.display_name().to_string(), attacker.level);
+                // special moves hit with special attack against special defense
+                let (attack_stat, attack) = match _move.attack_type {
+                    AttackType::Special => (Stat::SpecialAttack, attacker.stats.special_attack),
+                    _ => (Stat::Attack, attacker.stats.attack),
+                };
+                let attacker_attack = attacker_stages.apply(attack_stat, attack);
+                let type_boosted = held_items::boosts_move(attacker, _move);
+
+                if _move.power == 0 || targets.is_empty() {
+                    println!("{}'s {} used {}.", user, attacker_name, _move.name);
+                    return self.apply_move_effect(action, _move, &targets, player_party, renderer);
                 }
 
-                if defender.current_hp == 0 {
-                    let user = if !action.is_player { "Player" } else { "Enemy" };
-                    println!("{}'s {} fainted.", user, defender.name);
+                for target in &targets {
+                    let (defender, defender_stages, defender_display) = if action.is_player {
+                        (&mut self.enemy_party[self.enemy_active[*target].unwrap()], self.enemy_stages[*target], &mut self.enemy_displays[*target] as &mut dyn Display)
+                    } else {
+                        (&mut player_party[self.player_active[*target].unwrap()], self.player_stages[*target], &mut self.player_displays[*target] as &mut dyn Display)
+                    };
+
+                    let (defense_stat, defense) = match _move.attack_type {
+                        AttackType::Special => (Stat::SpecialDefense, defender.stats.special_defense),
+                        _ => (Stat::Defense, defender.stats.defense),
+                    };
+                    let defense = defender_stages.apply(defense_stat, defense);
+                    let mut damage = ((2 * attacker_level / 5 + 2) * _move.power * attacker_attack / defense) / 50 + 2;
+
+                    //spread moves are weaker when they hit more than one target
+                    if targets.len() > 1 {
+                        damage = damage * 3 / 4;
+                    }
+
+                    if type_boosted {
+                        damage = damage * 6 / 5;
+                    }
+
+                    println!("{}'s {} used {}. Dealt {} damage to {}.", user, attacker_name, _move.name, damage, defender.display_name());
+
+                    defender.current_hp = defender.current_hp.saturating_sub(damage);
+                    held_items::check_berry(defender);
+                    defender_display.update_health_bar(defender, renderer);
 
-                    return true;
+                    if defender.current_hp == 0 {
+                        println!("{}'s {} fainted.", foe, defender.display_name());
+                    }
                 }
+
+                return self.apply_move_effect(action, _move, &targets, player_party, renderer);
             },
             ActionType::Catch { ball_bonus } => {
+                let defender = match self.enemy_active[action.target] {
+                    Some(index) => &self.enemy_party[index],
+                    None => return None,
+                };
+
                 //if catch succeeds add to party and return true
                 //print max hp and current
                 let modifier = (3.0 * defender.stats.hp as f32 - 2.0 * defender.current_hp as f32) * defender.catch_rate as f32 / (3.0 * defender.stats.hp as f32);
@@ -327,29 +831,37 @@ impl Battle {
                 println!("Modifier: {}", modifier);
                 let catch_rate = ball_bonus * modifier;
                 println!("Catch rate: {}", catch_rate);
-                let mut rng = rand::thread_rng();
-                let val = rng.gen_range(0..256);
+                let val = self.rng.gen_range(0..256);
                 println!("Random value: {}", val);
                 if val < catch_rate as u32 {
-                    println!("Player caught {}.", defender.name);
-                    return true;
+                    println!("Player caught {}.", defender.display_name());
+                    return Some(BattleOutcome::Ended);
                 }
             },
             ActionType::Swap { slot } => {
                 //only switch if not fainted
-                println!("Player swapped to {}.", player_party[*slot as usize].name);
-                self.player_pokemon_index = *slot as usize;
-                self.player_display.swap(&player_party[self.player_pokemon_index], renderer);
-                self.ui.update_moves(&player_party[self.player_pokemon_index], renderer);
+                println!("Player swapped to {}.", player_party[*slot as usize].display_name());
+                self.player_active[action.slot] = Some(*slot as usize);
+                self.player_stages[action.slot] = StatStages::default();
+                self.player_displays[action.slot].swap(&player_party[*slot as usize], renderer);
+                if action.slot == self.choosing_slot {
+                    self.ui.update_moves(&player_party[*slot as usize], renderer);
+                }
             },
             ActionType::Run => {
+                let runner = &player_party[self.player_active[action.slot].unwrap()];
+                let fastest_foe = self.enemy_active.iter().flatten()
+                    .map(|&i| self.enemy_party[i].stats.speed)
+                    .max()
+                    .unwrap_or(1)
+                    .max(1);
 
-                let escape_chance = (attacker.stats.speed * 128 / defender.stats.speed + 30*self.escape_attempts).min(255); // Ensures the value is within 0 to 255.
+                let escape_chance = (runner.stats.speed * 128 / fastest_foe + 30*self.escape_attempts).min(255); // Ensures the value is within 0 to 255.
                 let chance_percentage = escape_chance as f64 / 256.0 * 100.0;
 
-                if rand::thread_rng().gen_range(0..256) < escape_chance {
+                if self.rng.gen_range(0..256) < escape_chance {
                     println!("Player escaped with {:.2}% chance.", chance_percentage);
-                    return true;
+                    return Some(BattleOutcome::Ended);
                 } else {
                     println!("Player failed to escape with {:.2}% chance.", chance_percentage);
                     self.escape_attempts += 1;
@@ -358,7 +870,98 @@ impl Battle {
             },
         }
 
-        false
+        None
+    }
+
+    // Returns how the battle ended if the effect ended it
+    fn apply_move_effect(&mut self, action: &Action, _move: &Move, targets: &[usize], player_party: &mut Vec<Pokemon>, renderer: &mut Renderer) -> Option<BattleOutcome> {
+        let effect = _move.effect?;
+
+        // (is_player side, slot) of everything the effect lands on
+        let affected: Vec<(bool, usize)> = if _move.target == Target::User {
+            vec![(action.is_player, action.slot)]
+        } else {
+            targets.iter().map(|&slot| (!action.is_player, slot)).collect()
+        };
+
+        match effect {
+            MoveEffect::StatChange { stat, stages } => {
+                for (is_player, slot) in affected {
+                    let (name, stat_stages) = if is_player {
+                        (player_party[self.player_active[slot].unwrap()].display_name().to_string(), &mut self.player_stages[slot])
+                    } else {
+                        (self.enemy_party[self.enemy_active[slot].unwrap()].display_name().to_string(), &mut self.enemy_stages[slot])
+                    };
+
+                    if stat_stages.change(stat, stages) {
+                        let direction = if stages > 0 { "rose" } else { "fell" };
+                        println!("{}'s {:?} {}.", name, stat, direction);
+                    } else {
+                        println!("{}'s {:?} won't go any further.", name, stat);
+                    }
+                }
+            },
+            MoveEffect::Inflict { status } => {
+                for (is_player, slot) in affected {
+                    let pokemon = if is_player {
+                        &mut player_party[self.player_active[slot].unwrap()]
+                    } else {
+                        &mut self.enemy_party[self.enemy_active[slot].unwrap()]
+                    };
+
+                    if pokemon.current_hp > 0 && pokemon.status.is_none() {
+                        pokemon.status = Some(status);
+                        if status == Status::Sleep {
+                            pokemon.sleep_turns = self.rng.gen_range(1..=3);
+                        }
+                        println!("{} is now affected by {:?}.", pokemon.display_name(), status);
+                    } else {
+                        println!("It had no effect on {}.", pokemon.display_name());
+                    }
+                }
+            },
+            MoveEffect::ForceSwitch => {
+                if self.battle_type == BattleType::Wild {
+                    println!("The battle was blown away!");
+                    return Some(BattleOutcome::Ended);
+                }
+
+                for (is_player, slot) in affected {
+                    if is_player {
+                        let bench: Vec<usize> = (0..player_party.len())
+                            .filter(|&i| player_party[i].current_hp > 0 && !self.player_active.contains(&Some(i)))
+                            .collect();
+                        if bench.is_empty() {
+                            println!("But it failed!");
+                            continue;
+                        }
+
+                        let index = bench[self.rng.gen_range(0..bench.len())];
+                        println!("{} was dragged out!", player_party[index].display_name());
+                        self.player_active[slot] = Some(index);
+                        self.player_stages[slot] = StatStages::default();
+                        self.player_displays[slot].swap(&player_party[index], renderer);
+                    } else {
+                        let owner = self.enemy_slot_owner(slot);
+                        let bench: Vec<usize> = (0..self.enemy_party.len())
+                            .filter(|&i| self.enemy_party[i].current_hp > 0 && self.enemy_owners[i] == owner && !self.enemy_active.contains(&Some(i)))
+                            .collect();
+                        if bench.is_empty() {
+                            println!("But it failed!");
+                            continue;
+                        }
+
+                        let index = bench[self.rng.gen_range(0..bench.len())];
+                        println!("{} was dragged out!", self.enemy_party[index].display_name());
+                        self.enemy_active[slot] = Some(index);
+                        self.enemy_stages[slot] = StatStages::default();
+                        self.enemy_displays[slot].swap(&self.enemy_party[index], renderer);
+                    }
+                }
+            },
+        }
+
+        None
     }
 
     pub fn draw(&self, player_party: &mut Vec<Pokemon>, renderer: &mut Renderer) {
@@ -373,17 +976,67 @@ impl Battle {
             return;
         }
 
+        // the whole battle screen is laid out in pixels
+        let mut list = DrawList::new();
+        list.set_space(Layer::Map, Space::Screen);
+        list.set_space(Layer::Entities, Space::Screen);
+        list.set_sort(Layer::Entities, Sort::Z);
+
+        list.push(Layer::Map, &self.background.texture);
+
+        for (slot, active) in self.enemy_active.iter().enumerate() {
+            if let Some(index) = active {
+                let mut sprite = Vec::new();
+                push_shifted(&mut sprite, &self.enemy_party[*index].front_sprite.texture, self.sprite_offset(slot));
+                list.push_sorted(Layer::Entities, ENEMY_SPRITE_Z, &sprite);
+            }
+        }
+        for (slot, active) in self.player_active.iter().enumerate() {
+            if let Some(index) = active {
+                let mut sprite = Vec::new();
+                push_shifted(&mut sprite, &player_party[*index].back_sprite.texture, self.sprite_offset(slot));
+                list.push_sorted(Layer::Entities, PLAYER_SPRITE_Z, &sprite);
+            }
+        }
+
         let mut instances = Vec::new();
-        instances.extend_from_slice(&self.background.texture);
-        instances.extend_from_slice(&self.enemy_party[self.enemy_pokemon_index].front_sprite.texture);
-        instances.extend_from_slice(&player_party[self.player_pokemon_index].back_sprite.texture);
         self.ui.draw(&mut instances);
-        self.player_display.draw(&mut instances);
-        self.enemy_display.draw(&mut instances);
 
-        let _ = renderer.render(&instances, false);
+        for (slot, display) in self.player_displays.iter().enumerate() {
+            if self.player_active[slot].is_some() {
+                display.draw(&mut instances);
+            }
+        }
+        for (slot, display) in self.enemy_displays.iter().enumerate() {
+            if self.enemy_active[slot].is_some() {
+                display.draw(&mut instances);
+            }
+        }
+        list.push(Layer::Ui, &instances);
+
+        let _ = renderer.draw(list);
     }
 
 }
 
+/// Returns true if the pokemon was hurt by poison
+fn poison_damage(pokemon: &mut Pokemon) -> bool {
+    if pokemon.status != Some(Status::Poison) || pokemon.current_hp == 0 {
+        return false;
+    }
 
+    pokemon.current_hp = pokemon.current_hp.saturating_sub((pokemon.stats.hp / 8).max(1));
+    println!("{} is hurt by poison.", pokemon.display_name());
+    if pokemon.current_hp == 0 {
+        println!("{} fainted.", pokemon.display_name());
+    }
+    true
+}
+
+fn push_shifted(instances: &mut Vec<Instance>, sprite: &[Instance], dx: f32) {
+    for instance in sprite {
+        let mut shifted = *instance;
+        shifted.model[3][0] += dx;
+        instances.push(shifted);
+    }
+}
@@ -13,13 +13,15 @@ pub struct PlayerDisplay {
     name: Font,
     health_bar: Sprite,
     exp_bar: Sprite,
+    offset: (f32, f32),
 }
 
 impl Display for PlayerDisplay {
 
     fn update_health_bar(&mut self, pokemon: &Pokemon, renderer: &mut Renderer) {
+        let (ox, oy) = self.offset;
         let hp_string = pokemon.current_hp.to_string() + "/";
-        let current_hp = Font::new(200.0, 95.0, &hp_string, false, "black_font", renderer);
+        let current_hp = Font::new(ox + 200.0, oy + 95.0, &hp_string, false, "black_font", renderer);
 
         let percent_hp = pokemon.current_hp as f32 / pokemon.stats.hp as f32;
         let y_offset = match percent_hp {
@@ -27,7 +29,7 @@ impl Display for PlayerDisplay {
             x if x > 0.25 => 1,
             _ => 2,
         };
-        self.health_bar = renderer.create_sprite(174.0, 91.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
+        self.health_bar = renderer.create_sprite(ox + 174.0, oy + 91.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
         self.current_hp = current_hp;
 
     }
@@ -44,17 +46,18 @@ impl Display for PlayerDisplay {
 }
 
 impl PlayerDisplay {
-    pub fn new(pokemon: &Pokemon, renderer: &mut Renderer) -> Self {
+    pub fn new(pokemon: &Pokemon, offset: (f32, f32), renderer: &mut Renderer) -> Self {
+        let (ox, oy) = offset;
 
-        let background = renderer.create_sprite(7.0 * 16.0, 4.0 * 16.0, 0, 12, 8, 3, "battle", 1.0, 1.0).expect("");
+        let background = renderer.create_sprite(ox + 7.0 * 16.0, oy + 4.0 * 16.0, 0, 12, 8, 3, "battle", 1.0, 1.0).expect("");
 
-        let name = Font::new(141.0, 77.0, &pokemon.name.to_uppercase(), true, "black_font", renderer);
+        let name = Font::new(ox + 141.0, oy + 77.0, &pokemon.display_name().to_uppercase(), true, "black_font", renderer);
         let level = "Lv".to_string() + &pokemon.level.to_string();
-        let level = Font::new(215.0, 77.0, &level, false, "black_font", renderer);
+        let level = Font::new(ox + 215.0, oy + 77.0, &level, false, "black_font", renderer);
 
         let hp_string = pokemon.current_hp.to_string() + "/";
-        let current_hp = Font::new(200.0, 95.0, &hp_string, false, "black_font", renderer);
-        let max_hp = Font::new(215.0, 95.0, &pokemon.stats.hp.to_string(), false, "black_font", renderer);
+        let current_hp = Font::new(ox + 200.0, oy + 95.0, &hp_string, false, "black_font", renderer);
+        let max_hp = Font::new(ox + 215.0, oy + 95.0, &pokemon.stats.hp.to_string(), false, "black_font", renderer);
 
         let percent_hp = pokemon.current_hp as f32 / pokemon.stats.hp as f32;
         let y_offset = match percent_hp {
@@ -62,10 +65,10 @@ impl PlayerDisplay {
             x if x > 0.25 => 1,
             _ => 2,
         };
-        let health_bar = renderer.create_sprite(174.0, 91.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
+        let health_bar = renderer.create_sprite(ox + 174.0, oy + 91.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
 
         let percent_exp = pokemon.experience as f32 / pokemon.experience_threshold as f32;
-        let exp_bar = renderer.create_sprite(158.0, 107.0, 3, 18, 3, 1, "battle", 1.0 * percent_exp, 1.0).expect("");
+        let exp_bar = renderer.create_sprite(ox + 158.0, oy + 107.0, 3, 18, 3, 1, "battle", 1.0 * percent_exp, 1.0).expect("");
 
         Self {
             background,
@@ -75,17 +78,19 @@ impl PlayerDisplay {
             name,
             health_bar,
             exp_bar,
+            offset,
         }
     }
 
     pub fn swap(&mut self, pokemon: &Pokemon, renderer: &mut Renderer) {
-        self.name = Font::new(141.0, 77.0, &pokemon.name.to_uppercase(), true, "black_font", renderer);
+        let (ox, oy) = self.offset;
+        self.name = Font::new(ox + 141.0, oy + 77.0, &pokemon.display_name().to_uppercase(), true, "black_font", renderer);
         let level = "Lv".to_string() + &pokemon.level.to_string();
-        self.level = Font::new(215.0, 77.0, &level, false, "black_font", renderer);
+        self.level = Font::new(ox + 215.0, oy + 77.0, &level, false, "black_font", renderer);
 
         let hp_string = pokemon.current_hp.to_string() + "/";
-        self.current_hp = Font::new(200.0, 95.0, &hp_string, false, "black_font", renderer);
-        self.max_hp = Font::new(215.0, 95.0, &pokemon.stats.hp.to_string(), false, "black_font", renderer);
+        self.current_hp = Font::new(ox + 200.0, oy + 95.0, &hp_string, false, "black_font", renderer);
+        self.max_hp = Font::new(ox + 215.0, oy + 95.0, &pokemon.stats.hp.to_string(), false, "black_font", renderer);
 
         let percent_hp = pokemon.current_hp as f32 / pokemon.stats.hp as f32;
         let y_offset = match percent_hp {
@@ -93,10 +98,10 @@ impl PlayerDisplay {
             x if x > 0.25 => 1,
             _ => 2,
         };
-        self.health_bar = renderer.create_sprite(174.0, 91.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
+        self.health_bar = renderer.create_sprite(ox + 174.0, oy + 91.0, 0, 18 + y_offset, 3, 1, "battle", 1.0 * percent_hp, 1.0).expect("");
 
         let percent_exp = pokemon.experience as f32 / pokemon.experience_threshold as f32;
-        self.exp_bar = renderer.create_sprite(158.0, 107.0, 3, 18, 3, 1, "battle", 1.0 * percent_exp, 1.0).expect("");
+        self.exp_bar = renderer.create_sprite(ox + 158.0, oy + 107.0, 3, 18, 3, 1, "battle", 1.0 * percent_exp, 1.0).expect("");
 
     }
 
@@ -0,0 +1,364 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+use crate::game::moves::Move;
+use crate::game::pokemon::{Pokemon, Status};
+use crate::renderer::Renderer;
+use super::{ActionType, BattleType, BattleFormat};
+
+// Replays are stored as plain text, one record per line:
+//
+//   type wild
+//   format single
+//   seed 1234
+//   player 5 20 0 Pikachu
+//   enemy 5 19 0 Bulbasaur
+//   item enemy 0 Miracle Seed
+//   move player 0 28 Thundershock
+//   move player 0 40 Growl
+//   status enemy 0 sleep 2
+//   action player 0 0 move Thundershock
+//   action enemy 0 0 move Tackle
+//
+// Team lines are level, hp and owning trainer, action lines carry the
+// active slot and target slot, item lines give a held item to a team member by index.
+// Move lines list a team member's moves in order with the PP left, status lines
+// give its status and the turns it has left to sleep.
+// Names go last on each line since some contain spaces.
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TeamMember {
+    pub name: String,
+    pub level: u32,
+    pub current_hp: u32,
+    pub owner: usize,
+    pub held_item: Option<String>,
+    // (name, pp left), empty in older replays which keep the species' moves
+    pub moves: Vec<(String, u32)>,
+    pub status: Option<Status>,
+    pub sleep_turns: u32,
+}
+
+#[derive(Clone)]
+pub struct RecordedAction {
+    pub is_player: bool,
+    pub slot: usize,
+    pub target: usize,
+    pub action_type: ActionType,
+}
+
+#[derive(Clone)]
+pub struct Replay {
+    pub battle_type: BattleType,
+    pub format: BattleFormat,
+    pub seed: u64,
+    pub player_team: Vec<TeamMember>,
+    pub enemy_team: Vec<TeamMember>,
+    pub actions: Vec<RecordedAction>,
+}
+
+impl TeamMember {
+    pub fn from_pokemon(pokemon: &Pokemon, owner: usize) -> Self {
+        Self {
+            name: pokemon.species.clone(),
+            level: pokemon.level,
+            current_hp: pokemon.current_hp,
+            owner,
+            held_item: pokemon.held_item.clone(),
+            moves: pokemon.moves.iter().map(|a_move| (a_move.name.clone(), a_move.current_pp)).collect(),
+            status: pokemon.status,
+            sleep_turns: pokemon.sleep_turns,
+        }
+    }
+
+    pub fn to_pokemon(&self, renderer: &mut Renderer) -> Pokemon {
+        let mut pokemon = Pokemon::new(self.name.clone(), self.level, renderer);
+        pokemon.current_hp = self.current_hp;
+        pokemon.held_item = self.held_item.clone();
+        pokemon.status = self.status;
+        pokemon.sleep_turns = self.sleep_turns;
+
+        if !self.moves.is_empty() {
+            pokemon.moves = self.moves.iter().map(|(name, pp)| {
+                let mut a_move = Move::new(name);
+                a_move.current_pp = *pp;
+                a_move
+            }).collect();
+        }
+
+        pokemon
+    }
+}
+
+fn status_name(status: Status) -> &'static str {
+    match status {
+        Status::Paralysis => "paralysis",
+        Status::Poison => "poison",
+        Status::Sleep => "sleep",
+    }
+}
+
+fn parse_status(name: &str) -> Option<Status> {
+    match name {
+        "paralysis" => Some(Status::Paralysis),
+        "poison" => Some(Status::Poison),
+        "sleep" => Some(Status::Sleep),
+        _ => None,
+    }
+}
+
+impl Replay {
+    pub fn new(battle_type: BattleType, format: BattleFormat, seed: u64, player_team: &[Pokemon], enemy_team: &[Pokemon], enemy_owners: &[usize]) -> Self {
+        Self {
+            battle_type,
+            format,
+            seed,
+            player_team: player_team.iter().map(|p| TeamMember::from_pokemon(p, 0)).collect(),
+            enemy_team: enemy_team.iter().zip(enemy_owners).map(|(p, owner)| TeamMember::from_pokemon(p, *owner)).collect(),
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, is_player: bool, slot: usize, target: usize, action_type: &ActionType) {
+        self.actions.push(RecordedAction {
+            is_player,
+            slot,
+            target,
+            action_type: action_type.clone(),
+        });
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut lines = Vec::new();
+
+        let battle_type = match self.battle_type {
+            BattleType::Wild => "wild",
+            BattleType::Trainer => "trainer",
+        };
+        lines.push(format!("type {}", battle_type));
+
+        let format = match self.format {
+            BattleFormat::Single => "single",
+            BattleFormat::Double => "double",
+            BattleFormat::Tag => "tag",
+        };
+        lines.push(format!("format {}", format));
+        lines.push(format!("seed {}", self.seed));
+
+        for member in &self.player_team {
+            lines.push(format!("player {} {} {} {}", member.level, member.current_hp, member.owner, member.name));
+        }
+        for member in &self.enemy_team {
+            lines.push(format!("enemy {} {} {} {}", member.level, member.current_hp, member.owner, member.name));
+        }
+        for (side, team) in [("player", &self.player_team), ("enemy", &self.enemy_team)] {
+            for (index, member) in team.iter().enumerate() {
+                if let Some(item) = &member.held_item {
+                    lines.push(format!("item {} {} {}", side, index, item));
+                }
+                for (name, pp) in &member.moves {
+                    lines.push(format!("move {} {} {} {}", side, index, pp, name));
+                }
+                if let Some(status) = member.status {
+                    lines.push(format!("status {} {} {} {}", side, index, status_name(status), member.sleep_turns));
+                }
+            }
+        }
+
+        for action in &self.actions {
+            let side = if action.is_player { "player" } else { "enemy" };
+            let action_type = match &action.action_type {
+                ActionType::Move { _move } => format!("move {}", _move.name),
+                ActionType::Catch { ball_bonus } => format!("catch {}", ball_bonus),
+                ActionType::Swap { slot } => format!("swap {}", slot),
+                ActionType::Run => "run".to_string(),
+            };
+            lines.push(format!("action {} {} {} {}", side, action.slot, action.target, action_type));
+        }
+
+        fs::write(path, lines.join("\n") + "\n").context("Failed to write replay file")?;
+
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).context("Failed to read replay file")?;
+
+        let mut battle_type = BattleType::Wild;
+        let mut format = BattleFormat::Single;
+        let mut seed = 0;
+        let mut player_team = Vec::new();
+        let mut enemy_team = Vec::new();
+        let mut actions = Vec::new();
+
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+
+            match key {
+                "type" => {
+                    battle_type = match rest {
+                        "wild" => BattleType::Wild,
+                        "trainer" => BattleType::Trainer,
+                        _ => return Err(anyhow!("Unknown battle type on line {}", line_number + 1)),
+                    };
+                }
+                "format" => {
+                    format = match rest {
+                        "single" => BattleFormat::Single,
+                        "double" => BattleFormat::Double,
+                        "tag" => BattleFormat::Tag,
+                        _ => return Err(anyhow!("Unknown battle format on line {}", line_number + 1)),
+                    };
+                }
+                "seed" => {
+                    seed = rest.parse().context("Invalid seed")?;
+                }
+                "player" | "enemy" => {
+                    let mut parts = rest.splitn(4, ' ');
+                    let level = parts.next().unwrap_or("").parse().context("Invalid level")?;
+                    let current_hp = parts.next().unwrap_or("").parse().context("Invalid hp")?;
+                    let owner = parts.next().unwrap_or("").parse().context("Invalid owner")?;
+                    let name = parts.next().ok_or_else(|| anyhow!("Missing name on line {}", line_number + 1))?.to_string();
+
+                    let member = TeamMember { name, level, current_hp, owner, held_item: None, moves: Vec::new(), status: None, sleep_turns: 0 };
+                    if key == "player" {
+                        player_team.push(member);
+                    } else {
+                        enemy_team.push(member);
+                    }
+                }
+                "item" => {
+                    let mut parts = rest.splitn(3, ' ');
+                    let team = if parts.next() == Some("player") { &mut player_team } else { &mut enemy_team };
+                    let index: usize = parts.next().unwrap_or("").parse().context("Invalid team index")?;
+                    let item = parts.next().ok_or_else(|| anyhow!("Missing item on line {}", line_number + 1))?;
+
+                    let member = team.get_mut(index).ok_or_else(|| anyhow!("No team member {} on line {}", index, line_number + 1))?;
+                    member.held_item = Some(item.to_string());
+                }
+                "move" => {
+                    let mut parts = rest.splitn(4, ' ');
+                    let team = if parts.next() == Some("player") { &mut player_team } else { &mut enemy_team };
+                    let index: usize = parts.next().unwrap_or("").parse().context("Invalid team index")?;
+                    let pp = parts.next().unwrap_or("").parse().context("Invalid pp")?;
+                    let name = parts.next().ok_or_else(|| anyhow!("Missing move on line {}", line_number + 1))?;
+
+                    let member = team.get_mut(index).ok_or_else(|| anyhow!("No team member {} on line {}", index, line_number + 1))?;
+                    member.moves.push((name.to_string(), pp));
+                }
+                "status" => {
+                    let mut parts = rest.splitn(4, ' ');
+                    let team = if parts.next() == Some("player") { &mut player_team } else { &mut enemy_team };
+                    let index: usize = parts.next().unwrap_or("").parse().context("Invalid team index")?;
+                    let status = parts.next().and_then(parse_status)
+                        .ok_or_else(|| anyhow!("Unknown status on line {}", line_number + 1))?;
+                    let sleep_turns = parts.next().unwrap_or("0").parse().context("Invalid sleep turns")?;
+
+                    let member = team.get_mut(index).ok_or_else(|| anyhow!("No team member {} on line {}", index, line_number + 1))?;
+                    member.status = Some(status);
+                    member.sleep_turns = sleep_turns;
+                }
+                "action" => {
+                    let mut parts = rest.splitn(4, ' ');
+                    let is_player = parts.next() == Some("player");
+                    let slot = parts.next().unwrap_or("").parse().context("Invalid slot")?;
+                    let target = parts.next().unwrap_or("").parse().context("Invalid target")?;
+                    let action = parts.next().unwrap_or("");
+                    let (kind, argument) = action.split_once(' ').unwrap_or((action, ""));
+
+                    let action_type = match kind {
+                        "move" => ActionType::Move { _move: Move::new(argument) },
+                        "catch" => ActionType::Catch { ball_bonus: argument.parse().context("Invalid ball bonus")? },
+                        "swap" => ActionType::Swap { slot: argument.parse().context("Invalid slot")? },
+                        "run" => ActionType::Run,
+                        _ => return Err(anyhow!("Unknown action on line {}", line_number + 1)),
+                    };
+
+                    actions.push(RecordedAction { is_player, slot, target, action_type });
+                }
+                _ => return Err(anyhow!("Unknown record on line {}", line_number + 1)),
+            }
+        }
+
+        if player_team.is_empty() || enemy_team.is_empty() {
+            return Err(anyhow!("Replay is missing a team"));
+        }
+
+        Ok(Self {
+            battle_type,
+            format,
+            seed,
+            player_team,
+            enemy_team,
+            actions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(name: &str, owner: usize) -> TeamMember {
+        TeamMember {
+            name: name.to_string(),
+            level: 12,
+            current_hp: 30,
+            owner,
+            held_item: None,
+            moves: vec![("Tackle".to_string(), 35), ("Sand Attack".to_string(), 0)],
+            status: None,
+            sleep_turns: 0,
+        }
+    }
+
+    #[test]
+    fn saved_replay_loads_back_the_same() {
+        let mut pikachu = member("Pikachu", 0);
+        pikachu.held_item = Some("Miracle Seed".to_string());
+        pikachu.status = Some(Status::Poison);
+        let mut staryu = member("Staryu", 0);
+        staryu.status = Some(Status::Sleep);
+        staryu.sleep_turns = 2;
+
+        let mut replay = Replay {
+            battle_type: BattleType::Trainer,
+            format: BattleFormat::Tag,
+            seed: 1234,
+            player_team: vec![pikachu, member("Pidgey", 0)],
+            enemy_team: vec![staryu, member("Goldeen", 1)],
+            actions: Vec::new(),
+        };
+        replay.record(true, 0, 1, &ActionType::Move { _move: Move::new("Thunder Wave") });
+        replay.record(false, 1, 0, &ActionType::Swap { slot: 1 });
+        replay.record(true, 1, 0, &ActionType::Run);
+
+        let path = std::env::temp_dir().join(format!("replay-round-trip-{}.txt", std::process::id()));
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path);
+        let _ = fs::remove_file(&path);
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.battle_type, replay.battle_type);
+        assert_eq!(loaded.format, replay.format);
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.player_team, replay.player_team);
+        assert_eq!(loaded.enemy_team, replay.enemy_team);
+
+        assert_eq!(loaded.actions.len(), replay.actions.len());
+        for (loaded, saved) in loaded.actions.iter().zip(&replay.actions) {
+            assert_eq!((loaded.is_player, loaded.slot, loaded.target), (saved.is_player, saved.slot, saved.target));
+            match (&loaded.action_type, &saved.action_type) {
+                (ActionType::Move { _move: a }, ActionType::Move { _move: b }) => assert_eq!(a.name, b.name),
+                (ActionType::Swap { slot: a }, ActionType::Swap { slot: b }) => assert_eq!(a, b),
+                (ActionType::Run, ActionType::Run) => {},
+                _ => panic!("action type changed"),
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use crate::game::moves::Stat;
+
+const MAX_STAGE: i32 = 6;
+
+// In battle stat modifiers, reset whenever a pokemon leaves the field
+#[derive(Clone, Copy, Default)]
+pub struct StatStages {
+    pub attack: i32,
+    pub defense: i32,
+    pub speed: i32,
+    pub special_attack: i32,
+    pub special_defense: i32,
+}
+
+impl StatStages {
+    pub fn get(&self, stat: Stat) -> i32 {
+        match stat {
+            Stat::Attack => self.attack,
+            Stat::Defense => self.defense,
+            Stat::Speed => self.speed,
+            Stat::SpecialAttack => self.special_attack,
+            Stat::SpecialDefense => self.special_defense,
+        }
+    }
+
+    /// Returns false if the stat couldn't go any further
+    pub fn change(&mut self, stat: Stat, stages: i32) -> bool {
+        let stage = match stat {
+            Stat::Attack => &mut self.attack,
+            Stat::Defense => &mut self.defense,
+            Stat::Speed => &mut self.speed,
+            Stat::SpecialAttack => &mut self.special_attack,
+            Stat::SpecialDefense => &mut self.special_defense,
+        };
+
+        let new_stage = (*stage + stages).clamp(-MAX_STAGE, MAX_STAGE);
+        let changed = new_stage != *stage;
+        *stage = new_stage;
+        changed
+    }
+
+    pub fn apply(&self, stat: Stat, value: u32) -> u32 {
+        let stage = self.get(stat);
+        let (numerator, denominator) = if stage >= 0 {
+            (2 + stage as u32, 2)
+        } else {
+            (2, 2 + (-stage) as u32)
+        };
+
+        (value * numerator / denominator).max(1)
+    }
+}
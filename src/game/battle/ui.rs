@@ -6,9 +6,10 @@ use crate::game::font::Font;
 use crate::game::pokemon::Pokemon;
 use winit::keyboard::KeyCode;
 use std::collections::HashMap;
-use crate::game::party::Party;
+use crate::game::party::{Party, PartyEvent};
 use std::time::Duration;
 use super::{Action, ActionType};
+use crate::game::moves::Target;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum MenuState {
@@ -17,6 +18,7 @@ pub enum MenuState {
     Bag,
     Pokemon,
     Run,
+    Target,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -30,8 +32,11 @@ pub enum FightState {
 pub enum UIMessage {
     Move {
        fight_state: FightState,
+       target: usize,
+    },
+    Catch {
+        target: usize,
     },
-    Catch,
     Swap {
         slot: u32,
     },
@@ -45,10 +50,14 @@ pub struct UI {
     fight_menu: Sprite,
     moves: Vec<Font>,
     valid_moves: [bool; 4],
+    move_targets: [Target; 4],
+    valid_targets: Vec<bool>,
+    target_selection: usize,
     pub menu_state: Option<MenuState>,
     menu_selection: Option<MenuState>,
     fight_selection: Option<FightState>,
     pub party: Option<Party>,
+    message: Vec<Font>,
 }
 
 impl UI {
@@ -59,6 +68,7 @@ impl UI {
         let fight_menu = renderer.create_sprite(0.0, 7.0 * 16.0, 0, 15, 15, 3, "battle", 1.0, 1.0).expect("");
 
         let (moves, valid_moves) = UI::initialize_moves(player_pokemon, renderer);
+        let move_targets = UI::initialize_targets(player_pokemon);
 
         Self {
             background,
@@ -67,10 +77,14 @@ impl UI {
             fight_menu,
             moves,
             valid_moves,
+            move_targets,
+            valid_targets: vec![true],
+            target_selection: 0,
             menu_state: Some(MenuState::Main),
             menu_selection: Some(MenuState::Fight),
             fight_selection: Some(FightState::Move1),
             party: None,
+            message: Vec::new(),
         }
     }
 
@@ -101,6 +115,21 @@ impl UI {
         (moves, valid_moves)
     }
 
+    fn initialize_targets(player_pokemon: &Pokemon) -> [Target; 4] {
+        let mut move_targets = [Target::Single; 4];
+
+        for (i, a_move) in player_pokemon.moves.iter().take(4).enumerate() {
+            move_targets[i] = a_move.target;
+        }
+
+        move_targets
+    }
+
+    /// Marks which enemy slots can be picked when a single target move is chosen
+    pub fn set_targets(&mut self, valid_targets: Vec<bool>) {
+        self.valid_targets = valid_targets;
+    }
+
     fn get_move_position(&self, index: usize) -> (f32, f32) {
         match index {
             0 => (14.0, 123.0),
@@ -118,6 +147,14 @@ impl UI {
             MenuState::Bag => (179.0, 120.0),
             MenuState::Pokemon => (123.0, 136.0),
             MenuState::Run => (179.0, 136.0),
+            MenuState::Target => (123.0, 120.0),
+        }
+    }
+
+    fn get_target_position(&self, slot: usize) -> (f32, f32) {
+        match slot {
+            0 => (100.0, 3.0),
+            _ => (100.0, 35.0),
         }
     }
 
@@ -143,11 +180,33 @@ impl UI {
                         self.selector.update_position(self.get_fight_position(selection).0, self.get_fight_position(selection).1);
                     }
                 }
+                MenuState::Target => {
+                    self.selector.update_position(self.get_target_position(self.target_selection).0, self.get_target_position(self.target_selection).1);
+                }
                 _ => {}
             }
         }
     }
 
+    // target selection opened from the bag picks which wild pokemon to throw at
+    fn catching(&self) -> bool {
+        self.menu_selection == Some(MenuState::Bag)
+    }
+
+    fn handle_target_input(&mut self, key: KeyCode) {
+        let next = match key {
+            KeyCode::KeyW | KeyCode::KeyA => self.target_selection.checked_sub(1),
+            KeyCode::KeyS | KeyCode::KeyD => Some(self.target_selection + 1),
+            _ => None,
+        };
+
+        if let Some(next) = next {
+            if self.valid_targets.get(next) == Some(&true) {
+                self.target_selection = next;
+            }
+        }
+    }
+
     fn handle_menu_input(&mut self, key: KeyCode) {
         let transitions: HashMap<(MenuState, KeyCode), MenuState> = [
             ((MenuState::Pokemon, KeyCode::KeyW), MenuState::Fight),
@@ -196,6 +255,7 @@ impl UI {
                     self.update_selector_position();
 
                     if key == KeyCode::KeyZ {
+                        self.message.clear();
                         self.menu_state = self.menu_selection;
                         self.update_selector_position();
 
@@ -216,33 +276,71 @@ impl UI {
                     }
                     if key == KeyCode::KeyZ {
                         if let Some(fight_selection) = self.fight_selection {
-                            return Some(UIMessage::Move { fight_state: fight_selection });
+                            let first_target = self.valid_targets.iter().position(|&valid| valid).unwrap_or(0);
+                            let target_count = self.valid_targets.iter().filter(|&&valid| valid).count();
+
+                            if self.move_targets[fight_selection as usize] == Target::Single && target_count > 1 {
+                                self.target_selection = first_target;
+                                self.menu_state = Some(MenuState::Target);
+                                self.update_selector_position();
+                            } else {
+                                return Some(UIMessage::Move { fight_state: fight_selection, target: first_target });
+                            }
+                        }
+                    }
+                }
+            },
+            Some(MenuState::Target) => {
+                if let Some(key) = input_manager.get_key_on_press() {
+                    self.handle_target_input(key);
+                    self.update_selector_position();
+
+                    if key == KeyCode::KeyX {
+                        self.menu_state = if self.catching() { Some(MenuState::Main) } else { Some(MenuState::Fight) };
+                        self.update_selector_position();
+                    }
+                    if key == KeyCode::KeyZ {
+                        if self.catching() {
+                            return Some(UIMessage::Catch { target: self.target_selection });
+                        }
+                        if let Some(fight_selection) = self.fight_selection {
+                            return Some(UIMessage::Move { fight_state: fight_selection, target: self.target_selection });
                         }
                     }
                 }
             },
             Some(MenuState::Bag) => {
-                return Some(UIMessage::Catch);
+                let first_target = self.valid_targets.iter().position(|&valid| valid).unwrap_or(0);
+                let target_count = self.valid_targets.iter().filter(|&&valid| valid).count();
+
+                if target_count > 1 {
+                    self.target_selection = first_target;
+                    self.menu_state = Some(MenuState::Target);
+                    self.update_selector_position();
+                } else {
+                    return Some(UIMessage::Catch { target: first_target });
+                }
             }
             Some(MenuState::Pokemon) => {
                 if let Some(party) = &mut self.party {
-                    let selected_slot = party.update(input_manager, dt, renderer);
-                    //only send message if pokemon isnt fainted
-                    if selected_slot < party.slots.len() as u32 {
-                        if !party.slots[selected_slot as usize].fainted {
+                    match party.update(pokemon, input_manager, dt, renderer) {
+                        //only send message if pokemon isnt fainted
+                        PartyEvent::Selected(selected_slot) => {
+                            if !party.slots[selected_slot].fainted {
+                                self.party = None;
+
+                                println!("Selected slot: {}", selected_slot);
+                                self.return_to_main();
+                                return Some(UIMessage::Swap { slot: selected_slot as u32 });
+                            } else {
+                                println!("Can't swap to a fainted Pokémon");
+                            }
+                        },
+                        PartyEvent::Cancel => {
                             self.party = None;
-
-                            println!("Selected slot: {}", selected_slot);
-                            self.return_to_main();
-                            return Some(UIMessage::Swap { slot: selected_slot });
-                        } else {
-                            println!("Can't swap to a fainted Pokémon");
-                        }
-                    }
-
-                    if selected_slot == 6 {
-                        self.party = None;
-                        self.menu_state = Some(MenuState::Main);
+                            self.menu_state = Some(MenuState::Main);
+                        },
+                        _ => {},
                     }
                 }
             }
@@ -263,6 +361,13 @@ impl UI {
         self.party = Some(Party::new(pokemon, true, true, renderer));
     }
 
+    /// Short text shown beside the main menu until the next choice is made
+    pub fn show_message(&mut self, lines: &[&str], renderer: &mut Renderer) {
+        self.message = lines.iter().enumerate()
+            .map(|(i, line)| Font::new(14.0, 123.0 + i as f32 * 15.0, line, true, "white_font", renderer))
+            .collect();
+    }
+
     pub fn return_to_main(&mut self) {
         self.menu_state = Some(MenuState::Main);
         self.update_selector_position();
@@ -285,17 +390,26 @@ impl UI {
 
         self.moves = moves;
         self.valid_moves = valid_moves;
+        self.move_targets = UI::initialize_targets(player_pokemon);
+        self.fight_selection = Some(FightState::Move1);
     }
 
     pub fn draw(&self, instances: &mut Vec<Instance>) {
         instances.extend_from_slice(&self.background.texture);
 
         match self.menu_state {
+            Some(MenuState::Target) if self.catching() => {
+                instances.extend_from_slice(&self.main_menu.texture);
+                instances.push(self.selector.texture[0]);
+            }
             Some(MenuState::Main | MenuState::Bag) => {
                 instances.extend_from_slice(&self.main_menu.texture);
                 instances.push(self.selector.texture[0]);
+                for line in &self.message {
+                    instances.extend_from_slice(&line.instanced());
+                }
             }
-            Some(MenuState::Fight) => {
+            Some(MenuState::Fight | MenuState::Target) => {
                 instances.extend_from_slice(&self.fight_menu.texture);
                 for move_font in &self.moves {
                     for sprite in &move_font.sprites {
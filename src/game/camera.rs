@@ -0,0 +1,108 @@
+use cgmath::{InnerSpace, Vector3};
+use std::time::Duration;
+use crate::game::Game;
+use crate::game::map_loader::{Edge, Map};
+use crate::renderer::Renderer;
+
+// tiles per second while panning
+const PAN_SPEED: f32 = 6.0;
+// the screen shows 15 by 10 tiles
+const VIEW_WIDTH: f32 = 15.0;
+const VIEW_HEIGHT: f32 = 10.0;
+
+#[derive(Clone, Copy, Debug)]
+pub enum CameraTarget {
+    Player,
+    Npc(u32),
+    Position(Vector3<f32>),
+}
+
+// Follows its target around the overworld. Cutscenes can point it somewhere
+// else, it then pans over smoothly instead of jumping
+pub struct Camera {
+    pub target: CameraTarget,
+    pub position: Vector3<f32>,
+    panning: bool,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            target: CameraTarget::Player,
+            position: Vector3::new(0.0, 0.0, 0.0),
+            panning: false,
+        }
+    }
+
+    pub fn pan_to(&mut self, target: CameraTarget) {
+        self.target = target;
+        self.panning = true;
+    }
+
+    pub fn panning(&self) -> bool {
+        self.panning
+    }
+
+    // sticks to the focus unless still panning over to it
+    fn update(&mut self, focus: Vector3<f32>, dt: Duration) -> Vector3<f32> {
+        if !self.panning {
+            self.position = focus;
+            return self.position;
+        }
+
+        let remaining = focus - self.position;
+        let step = PAN_SPEED * dt.as_secs_f32();
+        if remaining.magnitude() <= step {
+            self.position = focus;
+            self.panning = false;
+        } else {
+            self.position += remaining.normalize() * step;
+        }
+
+        self.position
+    }
+}
+
+// keeps one axis of the view between low and high, centring it when the map is smaller than the view
+fn clamp_axis(center: f32, low: f32, high: f32, view: f32, open_low: bool, open_high: bool) -> f32 {
+    if high - low <= view && !open_low && !open_high {
+        return (low + high) / 2.0;
+    }
+
+    let mut center = center;
+    if !open_high {
+        center = center.min(high - view / 2.0);
+    }
+    if !open_low {
+        center = center.max(low + view / 2.0);
+    }
+    center
+}
+
+/// Stops the view showing past the edges of the map. Edges joined to another map are left
+/// open so the neighbour can be seen
+pub fn clamp_to_map(position: Vector3<f32>, map: &Map) -> Vector3<f32> {
+    let open = |edge: Edge| map.connections.iter().any(|connection| connection.edge == edge);
+
+    // tiles are centred on their coordinates, row j of the map sits at y = -j
+    let (width, height) = (map.grid.width as f32, map.grid.height as f32);
+    let x = clamp_axis(position.x, -0.5, width - 0.5, VIEW_WIDTH, open(Edge::West), open(Edge::East));
+    let y = clamp_axis(position.y, 0.5 - height, 0.5, VIEW_HEIGHT, open(Edge::South), open(Edge::North));
+
+    Vector3::new(x, y, position.z)
+}
+
+impl Game {
+    pub fn update_camera(&mut self, renderer: &mut Renderer, dt: Duration) {
+        let focus = match self.camera.target {
+            CameraTarget::Player => self.player.position,
+            CameraTarget::Npc(id) => self.npcs.iter()
+                .find(|npc| npc.id.1 == id)
+                .map_or(self.player.position, |npc| npc.position),
+            CameraTarget::Position(position) => position,
+        };
+
+        let position = self.camera.update(clamp_to_map(focus, &self.map), dt);
+        renderer.update(position);
+    }
+}
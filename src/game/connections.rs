@@ -0,0 +1,86 @@
+use cgmath::Vector3;
+use crate::game::Game;
+use crate::game::map_loader::{Edge, Map};
+use crate::renderer::Renderer;
+
+// a map connected to the current one, drawn alongside it
+pub struct Neighbor {
+    pub map: Map,
+    // where the neighbour's origin sits in the current map's coordinates
+    pub offset: Vector3<f32>,
+}
+
+impl Game {
+    /// Loads the maps joined to the current one. A map that is already loaded,
+    /// such as the one the player just walked out of, is reused instead of read again.
+    /// The maps left behind release their tilesets, which are freed unless another map holds them
+    pub fn load_connections(&mut self, mut previous: Option<Map>, renderer: &mut Renderer) {
+        let mut left_behind: Vec<String> = self.neighbors.drain(..).map(|neighbor| neighbor.map.name).collect();
+
+        for connection in self.map.connections.clone() {
+            let mut map = match previous.take() {
+                Some(map) if map.name == connection.map_name => map,
+                other => {
+                    previous = other;
+                    Self::read_map(&connection.map_name, renderer)
+                }
+            };
+
+            let (width, height) = (self.map.grid.width as f32, self.map.grid.height as f32);
+            let (neighbor_width, neighbor_height) = (map.grid.width as f32, map.grid.height as f32);
+            let along = connection.offset as f32;
+            let offset = match connection.edge {
+                Edge::North => Vector3::new(along, neighbor_height, 0.0),
+                Edge::South => Vector3::new(along, -height, 0.0),
+                Edge::East => Vector3::new(width, -along, 0.0),
+                Edge::West => Vector3::new(-neighbor_width, -along, 0.0),
+            };
+            map.translate(offset);
+            map.upload(renderer);
+
+            println!("Connected {} to the {:?} of {}", connection.map_name, connection.edge, self.map.name);
+            self.neighbors.push(Neighbor { map, offset });
+        }
+
+        // released after the new neighbours are read so a tileset they share isn't reloaded
+        left_behind.extend(previous.map(|map| map.name));
+        for name in left_behind {
+            renderer.release_texture(&name);
+        }
+    }
+
+    /// Called once the player starts a step. Stepping off the map either carries on into
+    /// the connected map or is stopped at the edge
+    pub fn check_connections(&mut self, renderer: &mut Renderer) {
+        let target = self.player.target_position;
+        if self.map.contains(target) {
+            return;
+        }
+
+        let neighbor = self.neighbors.iter()
+            .position(|neighbor| neighbor.map.contains(target - neighbor.offset));
+
+        match neighbor {
+            Some(index) if !self.neighbors[index].map.is_blocked(target - self.neighbors[index].offset, self.player.surfing) => {
+                self.cross_connection(index, renderer);
+            },
+            _ => self.player.halt(),
+        }
+    }
+
+    // the neighbour becomes the current map without a fade, everything is moved into its coordinates
+    fn cross_connection(&mut self, index: usize, renderer: &mut Renderer) {
+        let Neighbor { mut map, offset } = self.neighbors.remove(index);
+        println!("Walking from {} into {}", self.map.name, map.name);
+
+        map.translate(-offset);
+        map.upload(renderer);
+        let previous = std::mem::replace(&mut self.map, map);
+
+        self.player.position -= offset;
+        self.player.target_position -= offset;
+
+        self.load_connections(Some(previous), renderer);
+        self.populate_map(renderer);
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::VecDeque;
+use winit::keyboard::KeyCode;
+use crate::game::font::Font;
+use crate::game::input_manager::InputManager;
+use crate::renderer::Renderer;
+use crate::renderer::sprite::Sprite;
+use crate::renderer::instance::Instance;
+
+const LINE_LENGTH: usize = 40;
+const LINES_PER_PAGE: usize = 2;
+
+pub struct Dialogue {
+    lines: VecDeque<String>,
+    background: Sprite,
+    text: Vec<Font>,
+}
+
+impl Dialogue {
+    pub fn new(messages: &[&str], renderer: &mut Renderer) -> Self {
+        let background = renderer.create_sprite(0.0, 7.0 * 16.0, 0, 7, 15, 3, "battle", 1.0, 1.0).expect("");
+
+        let mut lines = VecDeque::new();
+        for message in messages {
+            let wrapped = wrap(message);
+            //every message starts on a fresh page
+            let padding = (LINES_PER_PAGE - wrapped.len() % LINES_PER_PAGE) % LINES_PER_PAGE;
+            lines.extend(wrapped);
+            lines.extend(std::iter::repeat(String::new()).take(padding));
+        }
+
+        let mut dialogue = Self {
+            lines,
+            background,
+            text: Vec::new(),
+        };
+
+        dialogue.next_page(renderer);
+        dialogue
+    }
+
+    fn next_page(&mut self, renderer: &mut Renderer) -> bool {
+        if self.lines.is_empty() {
+            return false;
+        }
+
+        self.text = (0..LINES_PER_PAGE)
+            .filter_map(|i| self.lines.pop_front().map(|line| (i, line)))
+            .map(|(i, line)| Font::new(14.0, 123.0 + i as f32 * 15.0, &line, true, "white_font", renderer))
+            .collect();
+
+        true
+    }
+
+    /// Returns true once the last page has been dismissed
+    pub fn update(&mut self, input_manager: &mut InputManager, renderer: &mut Renderer) -> bool {
+        if let Some(KeyCode::KeyZ | KeyCode::KeyX) = input_manager.get_key_on_press() {
+            return !self.next_page(renderer);
+        }
+
+        false
+    }
+
+    // in screen space, drawn on the Ui layer of the overworld's draw list
+    pub fn instances(&self) -> Vec<Instance> {
+        let mut instances = Vec::new();
+        instances.extend_from_slice(&self.background.texture);
+        for line in &self.text {
+            instances.extend_from_slice(&line.instanced());
+        }
+        instances
+    }
+}
+
+pub fn wrap(message: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in message.split_whitespace() {
+        if !current.is_empty() && current.len() + 1 + word.len() > LINE_LENGTH {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+
+    lines
+}
@@ -0,0 +1,164 @@
+use cgmath::Vector3;
+use crate::game::{Game, GameState};
+use crate::game::map_loader::ObstacleKind;
+use crate::renderer::Renderer;
+
+// Moves that do something in the overworld. They are used by pressing Z against a tree,
+// boulder or water, or from the party menu, as long as the player has the badge for them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FieldMove {
+    Cut,
+    Surf,
+    Strength,
+}
+
+impl FieldMove {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Cut" => Some(FieldMove::Cut),
+            "Surf" => Some(FieldMove::Surf),
+            "Strength" => Some(FieldMove::Strength),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FieldMove::Cut => "Cut",
+            FieldMove::Surf => "Surf",
+            FieldMove::Strength => "Strength",
+        }
+    }
+
+    // story flag needed to use the move outside battle
+    pub fn badge(self) -> &'static str {
+        match self {
+            FieldMove::Cut => "Cascade Badge",
+            FieldMove::Surf => "Soul Badge",
+            FieldMove::Strength => "Rainbow Badge",
+        }
+    }
+}
+
+impl Game {
+    fn facing_tile(&self) -> Vector3<f32> {
+        self.player.position + self.player.facing_direction
+    }
+
+    // whether the move has something to act on where the player is facing
+    fn field_move_target(&self, field_move: FieldMove) -> bool {
+        let facing = self.facing_tile();
+        let obstacle = self.map.obstacle_at(facing).map(|index| self.map.obstacles[index].kind);
+
+        match field_move {
+            FieldMove::Cut => obstacle == Some(ObstacleKind::Tree),
+            FieldMove::Surf => !self.player.surfing && obstacle.is_none() && self.map.is_water(facing),
+            FieldMove::Strength => true,
+        }
+    }
+
+    /// Z pressed against a tree, boulder or water, returns false if there was nothing there
+    pub fn interact_field_obstacle(&mut self, renderer: &mut Renderer) -> bool {
+        let facing = self.facing_tile();
+        let field_move = match self.map.obstacle_at(facing).map(|index| self.map.obstacles[index].kind) {
+            Some(ObstacleKind::Tree) => FieldMove::Cut,
+            Some(ObstacleKind::Boulder) if self.strength => return false,
+            Some(ObstacleKind::Boulder) => FieldMove::Strength,
+            None if !self.player.surfing && self.map.is_water(facing) => FieldMove::Surf,
+            None => return false,
+        };
+
+        let user = self.player_pokemon.iter()
+            .find(|pokemon| pokemon.moves.iter().any(|known| known.name == field_move.name()))
+            .map(|pokemon| pokemon.display_name().to_string());
+
+        match user {
+            Some(user) if self.flags.contains(field_move.badge()) => self.use_field_move(field_move, &user, renderer),
+            _ => {
+                let message = match field_move {
+                    FieldMove::Cut => "This tree looks like it can be cut down.",
+                    FieldMove::Surf => "The water is a deep blue.",
+                    FieldMove::Strength => "It is a big boulder. A strong pokemon could push it.",
+                };
+                self.show_dialogue(&[message], renderer);
+            },
+        }
+
+        true
+    }
+
+    pub fn use_field_move_from_party(&mut self, slot: usize, field_move: FieldMove, renderer: &mut Renderer) {
+        self.state = GameState::Running;
+
+        if !self.flags.contains(field_move.badge()) {
+            let message = format!("The {} is needed to use {} outside battle.", field_move.badge(), field_move.name());
+            self.show_dialogue(&[&message], renderer);
+            return;
+        }
+
+        if !self.field_move_target(field_move) {
+            self.show_dialogue(&["There is nothing to use it on here."], renderer);
+            return;
+        }
+
+        let user = self.player_pokemon[slot].display_name().to_string();
+        self.use_field_move(field_move, &user, renderer);
+    }
+
+    fn use_field_move(&mut self, field_move: FieldMove, user: &str, renderer: &mut Renderer) {
+        println!("{} used {} in the field", user, field_move.name());
+
+        let facing = self.facing_tile();
+        let follow_up = match field_move {
+            FieldMove::Cut => {
+                if let Some(index) = self.map.obstacle_at(facing) {
+                    self.map.remove_obstacle(index);
+                }
+                "The tree was cut down."
+            },
+            FieldMove::Surf => {
+                self.player.set_surfing(true);
+                self.player.set_direction(self.player.facing_direction, &self.map);
+                "You hopped on its back."
+            },
+            FieldMove::Strength => {
+                self.strength = true;
+                "Boulders can now be pushed around."
+            },
+        };
+
+        let message = format!("{} used {}.", user, field_move.name());
+        self.show_dialogue(&[&message, follow_up], renderer);
+    }
+
+    // walking into a boulder once Strength is active pushes it a tile along the grid
+    pub fn push_boulder(&mut self) {
+        if !self.strength || !self.player.input_provided || self.player.position != self.player.target_position {
+            return;
+        }
+
+        let direction = self.player.facing_direction;
+        let facing = self.facing_tile();
+        let Some(index) = self.map.obstacle_at(facing) else {
+            return;
+        };
+        if self.map.obstacles[index].kind != ObstacleKind::Boulder {
+            return;
+        }
+
+        let beyond = facing + direction;
+        if self.map.is_blocked(beyond, false) {
+            return;
+        }
+
+        self.map.move_obstacle(index, beyond);
+        self.player.set_direction(direction, &self.map);
+    }
+
+    // stepping back onto land ends surfing
+    pub fn check_surfing(&mut self) {
+        if self.player.surfing && !self.map.is_water(self.player.position) {
+            self.player.set_surfing(false);
+        }
+    }
+}
@@ -14,7 +14,7 @@ impl Font {
         text: &str,
         left: bool,
         font_type: &str,
-        renderer: &Renderer,
+        renderer: &mut Renderer,
     ) -> Self {
 
         let mut sprites = Vec::new();
@@ -38,16 +38,16 @@ impl Font {
                 'I' => 8,  'i' => 36, '8' => 64,
                 'J' => 9,  'j' => 37, '9' => 65,
                 'K' => 10, 'k' => 38, ' ' => 66,
-                'L' => 11, 'l' => 39, '?' => 67,
+                'L' => 11, 'l' => 39, '?' => 85,
                 'M' => 12, 'm' => 40, '/' => 88,
                 'N' => 13, 'n' => 41, '-' => 89,
-                'O' => 14, 'o' => 42,
-                'P' => 15, 'p' => 43,
-                'Q' => 16, 'q' => 44,
-                'R' => 17, 'r' => 45,
-                'S' => 18, 's' => 46,
-                'T' => 19, 't' => 47,
-                'U' => 20, 'u' => 48,
+                'O' => 14, 'o' => 42, '!' => 84,
+                'P' => 15, 'p' => 43, ':' => 98,
+                'Q' => 16, 'q' => 44, '\'' => 99,
+                'R' => 17, 'r' => 45, '(' => 100,
+                'S' => 18, 's' => 46, ')' => 101,
+                'T' => 19, 't' => 47, '+' => 102,
+                'U' => 20, 'u' => 48, '=' => 103,
                 'V' => 21, 'v' => 49,
                 'W' => 22, 'w' => 50,
                 'X' => 23, 'x' => 51,
@@ -1,8 +1,15 @@
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum GameState {
     Paused,
     Running,
     Encounter,
     Party,
+    Dialogue,
+    Shop,
+    TrainerCard,
+    Pokedex,
+    Naming,
+    // a screen wipe is playing, input is ignored until it finishes
+    Transition,
     Debug,
 }
@@ -0,0 +1,140 @@
+// Renders screens offscreen and compares them against the PNGs in tests/golden.
+// UPDATE_GOLDENS=1 rewrites the goldens instead, a missing one fails the test otherwise.
+// Without any adapter, not even a software one, the tests fail unless SKIP_GOLDENS is set
+use std::path::PathBuf;
+use std::time::Duration;
+use futures::executor::block_on;
+use crate::game::Game;
+use crate::game::pokemon::Pokemon;
+use crate::assets;
+use crate::capture::RecordFormat;
+use crate::options::Options;
+use crate::renderer::Renderer;
+use crate::renderer::transition::Wipe;
+use crate::window::config::{WIDTH, HEIGHT};
+
+// software rasterisers round slightly differently from gpus
+const CHANNEL_TOLERANCE: u8 = 8;
+const MAX_DIFFERING_PIXELS: f32 = 0.001;
+
+fn headless_renderer(test: &str) -> Option<Renderer> {
+    // next to the manifest rather than above the test executable, which may be built anywhere
+    assets::init(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets")).expect("Failed to load assets");
+
+    match block_on(Renderer::new_headless(WIDTH, HEIGHT)) {
+        Ok(renderer) => Some(renderer),
+        Err(e) if std::env::var_os("SKIP_GOLDENS").is_some() => {
+            // the harness keeps stdout to itself for passing tests
+            eprintln!("SKIPPED golden test {}: {:?}", test, e);
+            None
+        },
+        Err(e) => panic!("No adapter to render {} with, set SKIP_GOLDENS=1 to skip the golden tests: {:?}", test, e),
+    }
+}
+
+fn new_game(renderer: &mut Renderer) -> Game {
+    let options = Options {
+        seed: 1,
+        record_dir: None,
+        replay: None,
+        capture_dir: PathBuf::from("captures"),
+        capture_seconds: 0,
+        capture_format: RecordFormat::Gif,
+        assets_dir: None,
+    };
+    let mut game = block_on(Game::new(renderer, &options));
+    game.update_camera(renderer, Duration::ZERO);
+    game
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.png", name))
+}
+
+fn assert_golden(name: &str, renderer: &mut Renderer) {
+    let pixels = renderer.read_pixels().expect("Failed to read back the frame");
+    let actual = image::RgbaImage::from_raw(WIDTH, HEIGHT, pixels).expect("Frame has the wrong size");
+    let path = golden_path(name);
+
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        actual.save(&path).unwrap();
+        println!("Wrote golden image {:?}", path);
+        return;
+    }
+
+    if !path.exists() {
+        let actual_path = path.with_extension("actual.png");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        actual.save(&actual_path).unwrap();
+        panic!("{} has no golden at {:?}, the frame was saved to {:?}, run with UPDATE_GOLDENS=1 to accept it", name, path, actual_path);
+    }
+
+    let expected = image::open(&path).unwrap().to_rgba8();
+    assert_eq!(expected.dimensions(), actual.dimensions(), "{} changed size", name);
+
+    let differing = expected.pixels().zip(actual.pixels())
+        .filter(|(a, b)| a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE))
+        .count();
+
+    if differing as f32 > MAX_DIFFERING_PIXELS * (WIDTH * HEIGHT) as f32 {
+        let actual_path = path.with_extension("actual.png");
+        actual.save(&actual_path).unwrap();
+        panic!("{} differs from its golden in {} pixels, the frame was saved to {:?}", name, differing, actual_path);
+    }
+}
+
+#[test]
+fn overworld() {
+    let Some(mut renderer) = headless_renderer("overworld") else {
+        return;
+    };
+
+    let mut game = new_game(&mut renderer);
+    game.draw(&mut renderer);
+
+    assert_golden("overworld", &mut renderer);
+}
+
+#[test]
+fn battle_ui() {
+    let Some(mut renderer) = headless_renderer("battle_ui") else {
+        return;
+    };
+
+    let mut game = new_game(&mut renderer);
+    let pokemon = Pokemon::new("Pidgey".to_string(), 3, &mut renderer);
+    game.start_encounter(pokemon, &mut renderer);
+    game.draw(&mut renderer);
+
+    assert_golden("battle_ui", &mut renderer);
+}
+
+#[test]
+fn party_screen() {
+    let Some(mut renderer) = headless_renderer("party_screen") else {
+        return;
+    };
+
+    let mut game = new_game(&mut renderer);
+    game.enter_party(&mut renderer);
+    game.draw(&mut renderer);
+
+    assert_golden("party_screen", &mut renderer);
+}
+
+#[test]
+fn white_fade_covers_the_frame() {
+    let Some(mut renderer) = headless_renderer("white_fade_covers_the_frame") else {
+        return;
+    };
+
+    // a fully faded frame is white all over, sprites included
+    let sprite = renderer.create_sprite(0.0, 0.0, 0, 0, 15, 10, "battle", 1.0, 1.0).expect("Failed to create the sprite");
+    renderer.set_wipe(Wipe::FadeWhite, 1.0);
+    renderer.render(&sprite.texture, false).expect("Failed to draw the frame");
+
+    let pixels = renderer.read_pixels().expect("Failed to read back the frame");
+    let white = pixels.chunks(4).filter(|pixel| pixel[..3].iter().all(|&channel| channel >= 255 - CHANNEL_TOLERANCE)).count();
+    assert_eq!(white, (WIDTH * HEIGHT) as usize, "the fade left some of the frame showing");
+}
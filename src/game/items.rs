@@ -0,0 +1,156 @@
+use crate::game::moves::{Move, Type};
+use crate::game::pokemon::{Pokemon, Status};
+
+#[derive(Clone, Copy)]
+pub enum ItemEffect {
+    Heal(u32),
+    Cure(Status),
+    // hidden machines teach a field move and are never used up
+    Teach(&'static str),
+}
+
+// what an item does while held in battle
+#[derive(Clone, Copy)]
+pub enum HeldEffect {
+    // eaten to restore hp once the holder drops to half health
+    Berry(u32),
+    // moves of the type hit a fifth harder
+    TypeBoost(Type),
+    // restores a sixteenth of max hp at the end of every turn
+    Leftovers,
+}
+
+#[derive(Clone)]
+pub struct Item {
+    pub name: String,
+    pub price: u32,
+    pub effect: Option<ItemEffect>,
+    pub held: Option<HeldEffect>,
+}
+
+impl Item {
+    pub fn new(name: &str) -> Self {
+        let held = match name {
+            "Oran Berry" => Some(HeldEffect::Berry(10)),
+            "Sitrus Berry" => Some(HeldEffect::Berry(30)),
+            "Leftovers" => Some(HeldEffect::Leftovers),
+            "Silk Scarf" => Some(HeldEffect::TypeBoost(Type::Normal)),
+            "Charcoal" => Some(HeldEffect::TypeBoost(Type::Fire)),
+            "Mystic Water" => Some(HeldEffect::TypeBoost(Type::Water)),
+            "Magnet" => Some(HeldEffect::TypeBoost(Type::Electric)),
+            "Miracle Seed" => Some(HeldEffect::TypeBoost(Type::Grass)),
+            "Poison Barb" => Some(HeldEffect::TypeBoost(Type::Poison)),
+            "Sharp Beak" => Some(HeldEffect::TypeBoost(Type::Flying)),
+            "Silver Powder" => Some(HeldEffect::TypeBoost(Type::Bug)),
+            _ => None,
+        };
+
+        let (price, effect) = match name {
+            "Poke Ball" => (200, None),
+            "Great Ball" => (600, None),
+            "Ultra Ball" => (1200, None),
+            "Potion" => (300, Some(ItemEffect::Heal(20))),
+            "Super Potion" => (700, Some(ItemEffect::Heal(50))),
+            "Hyper Potion" => (1500, Some(ItemEffect::Heal(200))),
+            "Antidote" => (100, Some(ItemEffect::Cure(Status::Poison))),
+            "Parlyz Heal" => (200, Some(ItemEffect::Cure(Status::Paralysis))),
+            "Awakening" => (250, Some(ItemEffect::Cure(Status::Sleep))),
+            "Repel" => (350, None),
+            "Escape Rope" => (550, None),
+            "HM01" => (0, Some(ItemEffect::Teach("Cut"))),
+            "HM03" => (0, Some(ItemEffect::Teach("Surf"))),
+            "HM04" => (0, Some(ItemEffect::Teach("Strength"))),
+            "Oran Berry" | "Sitrus Berry" => (20, None),
+            "Leftovers" => (200, None),
+            "Silk Scarf" | "Charcoal" | "Mystic Water" | "Magnet" | "Miracle Seed" | "Poison Barb" | "Sharp Beak" | "Silver Powder" => (1000, None),
+            _ => (0, None),
+        };
+
+        Self {
+            name: name.to_string(),
+            price,
+            effect,
+            held,
+        }
+    }
+
+    /// Returns false if the item would have no effect, so it isn't used up
+    pub fn use_on(&self, pokemon: &mut Pokemon) -> bool {
+        match self.effect {
+            Some(ItemEffect::Heal(amount)) => {
+                if pokemon.current_hp == 0 || pokemon.current_hp == pokemon.stats.hp {
+                    return false;
+                }
+                pokemon.current_hp = (pokemon.current_hp + amount).min(pokemon.stats.hp);
+                true
+            },
+            Some(ItemEffect::Cure(status)) => {
+                if pokemon.status != Some(status) {
+                    return false;
+                }
+                pokemon.status = None;
+                true
+            },
+            Some(ItemEffect::Teach(move_name)) => {
+                if pokemon.moves.iter().any(|known| known.name == move_name) {
+                    return false;
+                }
+                //with no room left the oldest move is forgotten
+                if pokemon.moves.len() >= 4 {
+                    let forgotten = pokemon.moves.remove(0);
+                    println!("{} forgot {}.", pokemon.display_name(), forgotten.name);
+                }
+                pokemon.moves.push(Move::new(move_name));
+                println!("{} learned {}.", pokemon.display_name(), move_name);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn consumed(&self) -> bool {
+        !matches!(self.effect, Some(ItemEffect::Teach(_)))
+    }
+
+    // shops buy items back at half price
+    pub fn sell_price(&self) -> u32 {
+        self.price / 2
+    }
+}
+
+pub struct Inventory {
+    pub items: Vec<(String, u32)>, // (item name, count)
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self {
+            items: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, name: &str, amount: u32) {
+        match self.items.iter_mut().find(|(item, _)| item == name) {
+            Some((_, count)) => *count += amount,
+            None => self.items.push((name.to_string(), amount)),
+        }
+    }
+
+    /// Returns false without changing anything if there aren't enough
+    pub fn remove(&mut self, name: &str, amount: u32) -> bool {
+        let Some(index) = self.items.iter().position(|(item, _)| item == name) else {
+            return false;
+        };
+
+        if self.items[index].1 < amount {
+            return false;
+        }
+
+        self.items[index].1 -= amount;
+        if self.items[index].1 == 0 {
+            self.items.remove(index);
+        }
+
+        true
+    }
+}
@@ -1,19 +1,9 @@
 use crate::renderer::instance::Instance;
+use crate::renderer::{Renderer, StaticBatch};
 use cgmath::Vector3;
-
-#[derive(Clone)]
-pub struct Rectangle {
-    pub x: f32,
-    pub y: f32,
-    pub width: f32,
-    pub height: f32,
-}
-
-impl Rectangle {
-    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
-        Self { x, y, width, height }
-    }
-}
+use std::collections::HashSet;
+use crate::game::tile_grid::{Cell, TileGrid};
+use crate::game::items::Item;
 
 #[derive(Clone)]
 pub struct Spawn {
@@ -34,6 +24,20 @@ pub struct Npc {
     pub interaction: String,
     pub los: u32,
     pub path_id: Option<u32>,
+    pub stock: Vec<String>,
+    // only placed once this flag is set
+    pub requires: Option<String>,
+    // removed once this flag is set, e.g. someone blocking a route until a badge is earned
+    pub until: Option<String>,
+    // see game::script for the command list
+    pub script: Option<String>,
+}
+
+impl Npc {
+    pub fn present(&self, flags: &HashSet<String>) -> bool {
+        self.requires.as_ref().map_or(true, |flag| flags.contains(flag))
+            && self.until.as_ref().map_or(true, |flag| !flags.contains(flag))
+    }
 }
 
 #[derive(Clone)]
@@ -41,19 +45,74 @@ pub struct Interaction {
     pub name: String,
     pub x: f32,
     pub y: f32,
+    pub script: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct Door {
-    pub rectangle: Rectangle,
+    pub x: f32,
+    pub y: f32,
     pub name: String,
     pub location: u32,
+    // story flag needed before the door can be used
+    pub requires: Option<String>,
 }
 
+// runs a script when the player steps onto it
 #[derive(Clone)]
-pub struct Grass {
+pub struct Trigger {
     pub x: f32,
     pub y: f32,
+    pub script: String,
+    // the trigger stops firing once this flag is set
+    pub until: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ObstacleKind {
+    // removed with Cut
+    Tree,
+    // pushed along the grid once Strength is used
+    Boulder,
+}
+
+// tile objects in the "Obstacles" layer, named Tree or Boulder
+#[derive(Clone)]
+pub struct Obstacle {
+    pub kind: ObstacleKind,
+    pub x: f32,
+    pub y: f32,
+    pub tile: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Terrain {
+    // solid except when walking off it in this direction, the player hops over it
+    Ledge(Vector3<f32>),
+    // the player slides until something stops them
+    Ice,
+    // sends the player spinning in this direction until something stops them
+    Spin(Vector3<f32>),
+    // carries the player a tile in this direction
+    Conveyor(Vector3<f32>),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Edge {
+    North,
+    South,
+    East,
+    West,
+}
+
+// another map joined to this one along an edge, read from the map properties
+// "north", "south", "east" and "west" with the neighbouring map's name, and
+// "<edge>_offset" for how many tiles the neighbour is shifted along the seam
+#[derive(Clone)]
+pub struct Connection {
+    pub edge: Edge,
+    pub map_name: String,
+    pub offset: i32,
 }
 
 #[derive(Clone)]
@@ -70,20 +129,35 @@ pub struct Path {
     pub direction: String,
 }
 
+// the tile layers kept on the gpu, see Map::upload
+#[derive(Default)]
+pub struct LayerBatches {
+    pub background: StaticBatch,
+    pub ground: StaticBatch,
+    pub foreground: StaticBatch,
+    pub aboveground: StaticBatch,
+}
+
 pub struct Map {
     pub background: Vec<Instance>,
     pub ground: Vec<Instance>,
     pub foreground: Vec<Instance>,
     pub aboveground: Vec<Instance>,
-    pub collisions: Vec<Rectangle>,
+    pub layers: LayerBatches,
+    // solid, water, grass, terrain, door and interaction lookups by tile
+    pub grid: TileGrid,
+    pub obstacles: Vec<Obstacle>,
     pub doors: Vec<Door>,
     pub spawns: Vec<Spawn>,
-    pub grasses: Vec<Grass>,
     pub npcs: Vec<Npc>,
     pub interactions: Vec<Interaction>,
     pub animated: Vec<Animated>,
     pub paths: Vec<Path>,
+    pub triggers: Vec<Trigger>,
+    pub connections: Vec<Connection>,
     pub name: String,
+    // texture array layer holding the map's tileset
+    pub atlas_index: u32,
 }
 
 impl Map {
@@ -92,14 +166,15 @@ impl Map {
         let mut ground = Vec::new();
         let mut foreground = Vec::new();
         let mut aboveground = Vec::new();
-        let mut collisions = Vec::new();
+        let mut grid = TileGrid::new(map.width, map.height);
+        let mut obstacles = Vec::new();
         let mut doors = Vec::new();
         let mut spawns = Vec::new();
-        let mut grasses = Vec::new();
         let mut npcs = Vec::new();
         let mut interactions = Vec::new();
         let mut animated = Vec::new();
         let mut paths = Vec::new();
+        let mut triggers = Vec::new();
 
         println!("Atlas index for new map is: {}", atlas_index);
 
@@ -107,6 +182,8 @@ impl Map {
             if let tiled::LayerType::Tiles(tile_layer) = layer.layer_type() {
                 println!("Layer name: {}", layer.name);
 
+                Self::push_terrain(&mut grid, &tile_layer);
+
                 match layer.name.as_str() {
                     "Background" => {
                         Self::push_instances(&mut background, &tile_layer, atlas_index);
@@ -121,7 +198,10 @@ impl Map {
                         Self::push_instances(&mut aboveground, &tile_layer, atlas_index);
                     }
                     "Collision" => {
-                        Self::push_collisions(&mut collisions, &tile_layer);
+                        Self::mark_tiles(&mut grid, &tile_layer, |cell| cell.solid = true);
+                    }
+                    "Water" => {
+                        Self::mark_tiles(&mut grid, &tile_layer, |cell| cell.water = true);
                     }
                     _ => {}
                 }
@@ -136,7 +216,7 @@ impl Map {
                         Self::push_doors(&mut doors, &object_layer);
                     }
                     "Grasses" => {
-                        Self::push_grasses(&mut grasses, &object_layer);
+                        Self::push_grasses(&mut grid, &object_layer);
                     }
                     "Npcs" => {
                         Self::push_npcs(&mut npcs, &object_layer);
@@ -150,6 +230,12 @@ impl Map {
                     "Paths" => {
                         Self::push_paths(&mut paths, &object_layer);
                     }
+                    "Triggers" => {
+                        Self::push_triggers(&mut triggers, &object_layer);
+                    }
+                    "Obstacles" => {
+                        Self::push_obstacles(&mut obstacles, &object_layer);
+                    }
                     _ => {}
                 }
 
@@ -157,21 +243,165 @@ impl Map {
 
         }
 
+        for (index, door) in doors.iter().enumerate() {
+            if let Some(cell) = grid.cell_mut(Vector3::new(door.x, door.y, 0.0)) {
+                cell.door = Some(index);
+            }
+        }
+
+        for (index, interaction) in interactions.iter().enumerate() {
+            if let Some(cell) = grid.cell_mut(Vector3::new(interaction.x, interaction.y, 0.0)) {
+                cell.interaction = Some(index);
+            }
+        }
+
+        for (index, trigger) in triggers.iter().enumerate() {
+            if let Some(cell) = grid.cell_mut(Vector3::new(trigger.x, trigger.y, 0.0)) {
+                cell.trigger = Some(index);
+            }
+        }
+
+        Self::index_obstacles(&mut grid, &obstacles);
+
+        let mut connections = Vec::new();
+        for (edge, key) in [(Edge::North, "north"), (Edge::South, "south"), (Edge::East, "east"), (Edge::West, "west")] {
+            if let Some(tiled::PropertyValue::StringValue(map_name)) = map.properties.get(key) {
+                let offset = match map.properties.get(&format!("{}_offset", key)) {
+                    Some(tiled::PropertyValue::IntValue(val)) => *val,
+                    _ => 0,
+                };
+                connections.push(Connection { edge, map_name: map_name.clone(), offset });
+            }
+        }
+
         Self {
             background,
             ground,
             foreground,
             aboveground,
-            collisions,
+            layers: LayerBatches::default(),
+            grid,
+            obstacles,
             doors,
             spawns,
-            grasses,
             npcs,
             interactions,
             animated,
             paths,
+            triggers,
+            connections,
             name,
+            atlas_index,
+        }
+    }
+
+    /// Sends the tile layers to the gpu, needed again after they are moved
+    pub fn upload(&mut self, renderer: &Renderer) {
+        self.layers = LayerBatches {
+            background: renderer.create_static_batch(&self.background),
+            ground: renderer.create_static_batch(&self.ground),
+            foreground: renderer.create_static_batch(&self.foreground),
+            aboveground: renderer.create_static_batch(&self.aboveground),
+        };
+    }
+
+    pub fn contains(&self, position: Vector3<f32>) -> bool {
+        self.grid.contains(position)
+    }
+
+    fn tile_instances_mut(&mut self) -> impl Iterator<Item = &mut Instance> {
+        self.background.iter_mut()
+            .chain(self.ground.iter_mut())
+            .chain(self.foreground.iter_mut())
+            .chain(self.aboveground.iter_mut())
+    }
+
+    /// Moves the tile instances, used to draw a connected map next to the current one
+    pub fn translate(&mut self, offset: Vector3<f32>) {
+        let translation = cgmath::Matrix4::from_translation(offset);
+        for instance in self.tile_instances_mut() {
+            instance.model = (translation * cgmath::Matrix4::from(instance.model)).into();
+        }
+    }
+
+    pub fn is_water(&self, position: Vector3<f32>) -> bool {
+        self.grid.cell(position).water
+    }
+
+    pub fn is_grass(&self, position: Vector3<f32>) -> bool {
+        self.grid.cell(position).grass
+    }
+
+    pub fn door_at(&self, position: Vector3<f32>) -> Option<&Door> {
+        self.grid.cell(position).door.map(|index| &self.doors[index])
+    }
+
+    pub fn interaction_at(&self, position: Vector3<f32>) -> Option<&Interaction> {
+        self.grid.cell(position).interaction.map(|index| &self.interactions[index])
+    }
+
+    pub fn npc_at(&self, position: Vector3<f32>) -> Option<u32> {
+        self.grid.cell(position).npc
+    }
+
+    pub fn obstacle_at(&self, position: Vector3<f32>) -> Option<usize> {
+        self.grid.cell(position).obstacle
+    }
+
+    pub fn trigger_at(&self, position: Vector3<f32>) -> Option<&Trigger> {
+        self.grid.cell(position).trigger.map(|index| &self.triggers[index])
+    }
+
+    /// Takes an obstacle off the map, like a tree that was cut down
+    pub fn remove_obstacle(&mut self, index: usize) {
+        self.obstacles.remove(index);
+        Self::index_obstacles(&mut self.grid, &self.obstacles);
+    }
+
+    pub fn move_obstacle(&mut self, index: usize, position: Vector3<f32>) {
+        let obstacle = &mut self.obstacles[index];
+        if let Some(cell) = self.grid.cell_mut(Vector3::new(obstacle.x, obstacle.y, 0.0)) {
+            cell.obstacle = None;
         }
+
+        obstacle.x = position.x;
+        obstacle.y = position.y;
+        if let Some(cell) = self.grid.cell_mut(position) {
+            cell.obstacle = Some(index);
+        }
+    }
+
+    // removing an obstacle shifts the ones after it down, so every cell is marked again
+    fn index_obstacles(grid: &mut TileGrid, obstacles: &[Obstacle]) {
+        grid.clear_obstacles();
+        for (index, obstacle) in obstacles.iter().enumerate() {
+            if let Some(cell) = grid.cell_mut(Vector3::new(obstacle.x, obstacle.y, 0.0)) {
+                cell.obstacle = Some(index);
+            }
+        }
+    }
+
+    pub fn terrain_at(&self, position: Vector3<f32>) -> Option<Terrain> {
+        self.grid.cell(position).terrain
+    }
+
+    /// Whether the tile can't be walked onto, water only lets a surfing player through.
+    /// Ledges count as blocked, hopping down them is handled by the player
+    pub fn is_blocked(&self, position: Vector3<f32>, surfing: bool) -> bool {
+        let cell = self.grid.cell(position);
+        cell.solid
+            || cell.npc.is_some()
+            || matches!(cell.terrain, Some(Terrain::Ledge(_)))
+            || (!surfing && cell.water)
+            || cell.obstacle.is_some()
+    }
+
+    pub fn obstacle_instances(&self, atlas_index: u32) -> Vec<Instance> {
+        self.obstacles.iter().map(|obstacle| Instance {
+            model: cgmath::Matrix4::from_translation(Vector3::new(obstacle.x, obstacle.y, 0.0)).into(),
+            tex_index: obstacle.tile,
+            atlas_index,
+        }).collect()
     }
 
     fn push_instances(instances: &mut Vec<Instance>, tile_layer: &tiled::TileLayer, atlas_index: u32) {
@@ -188,21 +418,71 @@ impl Map {
         }
     }
 
-    fn push_collisions(collisions: &mut Vec<Rectangle>, tile_layer: &tiled::TileLayer) {
+    fn mark_tiles(grid: &mut TileGrid, tile_layer: &tiled::TileLayer, mark: fn(&mut Cell)) {
         for i in 0..tile_layer.width().unwrap() {
             for j in 0..tile_layer.height().unwrap() {
                 if let Some(_tile) = tile_layer.get_tile(i as i32, j as i32) {
-                    collisions.push(Rectangle::new(i as f32, -1.0 * j as f32, 1.0, 1.0));
+                    if let Some(cell) = grid.cell_mut(Vector3::new(i as f32, -1.0 * j as f32, 0.0)) {
+                        mark(cell);
+                    }
+                }
+            }
+        }
+    }
+
+    // tiles whose tileset entry has a "terrain" property (ledge, ice, spin or conveyor)
+    // and, apart from ice, a "direction" property using the same numbering as spawns
+    fn push_terrain(grid: &mut TileGrid, tile_layer: &tiled::TileLayer) {
+        for i in 0..tile_layer.width().unwrap() {
+            for j in 0..tile_layer.height().unwrap() {
+                let Some(tile) = tile_layer.get_tile(i as i32, j as i32).and_then(|tile| tile.get_tile()) else {
+                    continue;
+                };
+
+                let direction = Self::direction_property(&tile.properties);
+                let kind = match tile.properties.get("terrain") {
+                    Some(tiled::PropertyValue::StringValue(val)) => val.as_str(),
+                    _ => continue,
+                };
+
+                let tile_terrain = match kind {
+                    "ledge" => Terrain::Ledge(direction),
+                    "ice" => Terrain::Ice,
+                    "spin" => Terrain::Spin(direction),
+                    "conveyor" => Terrain::Conveyor(direction),
+                    other => {
+                        println!("Unknown terrain {}", other);
+                        continue;
+                    }
+                };
+
+                if let Some(cell) = grid.cell_mut(Vector3::new(i as f32, -1.0 * j as f32, 0.0)) {
+                    cell.terrain = Some(tile_terrain);
                 }
             }
         }
     }
 
-    fn push_grasses(grasses: &mut Vec<Grass>, object_layer: &tiled::ObjectLayer) {
+    fn direction_property(properties: &tiled::Properties) -> Vector3<f32> {
+        match properties.get("direction") {
+            Some(tiled::PropertyValue::IntValue(val)) => match val {
+                0 => Vector3::new(0.0, 1.0, 0.0),
+                1 => Vector3::new(1.0, 0.0, 0.0),
+                2 => Vector3::new(0.0, -1.0, 0.0),
+                3 => Vector3::new(-1.0, 0.0, 0.0),
+                _ => Vector3::new(0.0, 1.0, 0.0),
+            },
+            _ => Vector3::new(0.0, 1.0, 0.0),
+        }
+    }
+
+    fn push_grasses(grid: &mut TileGrid, object_layer: &tiled::ObjectLayer) {
         for object in object_layer.objects() {
             let x = object.x as f32 / 16.0;
             let y = -1.0 * object.y as f32 / 16.0 + 1.0;
-            grasses.push(Grass { x, y });
+            if let Some(cell) = grid.cell_mut(Vector3::new(x, y, 0.0)) {
+                cell.grass = true;
+            }
         }
     }
 
@@ -210,16 +490,7 @@ impl Map {
         for object in object_layer.objects() {
 
             let name = object.name.clone();
-            let direction = match object.properties.get("direction") {
-                Some(tiled::PropertyValue::IntValue(val)) => match val {
-                    0 => Vector3::new(0.0, 1.0, 0.0),
-                    1 => Vector3::new(1.0, 0.0, 0.0),
-                    2 => Vector3::new(0.0, -1.0, 0.0),
-                    3 => Vector3::new(-1.0, 0.0, 0.0),
-                    _ => Vector3::new(0.0, 1.0, 0.0),
-                },
-                _ => Vector3::new(0.0, 1.0, 0.0),
-            };
+            let direction = Self::direction_property(&object.properties);
 
             let x = object.x as f32 / 16.0;
             let y = -1.0 * object.y as f32 / 16.0 + 1.0;
@@ -253,22 +524,20 @@ impl Map {
         }
     }
 
+    fn string_property(object: &tiled::Object, name: &str) -> Option<String> {
+        match object.properties.get(name) {
+            Some(tiled::PropertyValue::StringValue(val)) if !val.is_empty() => Some(val.clone()),
+            _ => None,
+        }
+    }
+
     fn push_npcs(npcs: &mut Vec<Npc>, object_layer: &tiled::ObjectLayer) {
         for object in object_layer.objects() {
             let name = object.name.clone();
             let x = object.x as f32 / 16.0;
             let y = -1.0 * object.y as f32 / 16.0 + 1.0;
 
-            let direction = match object.properties.get("direction") {
-                Some(tiled::PropertyValue::IntValue(val)) => match val {
-                    0 => Vector3::new(0.0, 1.0, 0.0),
-                    1 => Vector3::new(1.0, 0.0, 0.0),
-                    2 => Vector3::new(0.0, -1.0, 0.0),
-                    3 => Vector3::new(-1.0, 0.0, 0.0),
-                    _ => Vector3::new(0.0, 1.0, 0.0),
-                },
-                _ => Vector3::new(0.0, 1.0, 0.0),
-            };
+            let direction = Self::direction_property(&object.properties);
 
             let interaction = match object.properties.get("interaction") {
                 Some(tiled::PropertyValue::StringValue(val)) => val.clone(),
@@ -285,9 +554,40 @@ impl Map {
                 _ => None,
             };
 
+            // comma separated item names sold by a shop clerk, anything without a price is left out
+            let stock = match object.properties.get("stock") {
+                Some(tiled::PropertyValue::StringValue(val)) => val.split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| {
+                        let priced = Item::new(item).price > 0;
+                        if !priced {
+                            println!("Unknown shop item {} sold by {}", item, name);
+                        }
+                        priced
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+
             let id = object.id();
 
-            npcs.push(Npc {name, id, x, y, direction, interaction, los, path_id});
+            let requires = Self::string_property(&object, "requires");
+            let until = Self::string_property(&object, "until");
+            let script = Self::string_property(&object, "script");
+
+            npcs.push(Npc {name, id, x, y, direction, interaction, los, path_id, stock, requires, until, script});
+        }
+    }
+
+    fn push_triggers(triggers: &mut Vec<Trigger>, object_layer: &tiled::ObjectLayer) {
+        for object in object_layer.objects() {
+            let x = object.x as f32 / 16.0;
+            let y = -1.0 * object.y as f32 / 16.0 + 1.0;
+
+            if let Some(script) = Self::string_property(&object, "script") {
+                let until = Self::string_property(&object, "until");
+                triggers.push(Trigger { x, y, script, until });
+            }
         }
     }
 
@@ -296,7 +596,8 @@ impl Map {
             let name = object.name.clone();
             let x = object.x as f32 / 16.0;
             let y = -1.0 * object.y as f32 / 16.0 + 1.0;
-            interactions.push(Interaction {name, x, y});
+            let script = Self::string_property(&object, "script");
+            interactions.push(Interaction {name, x, y, script});
         }
     }
 
@@ -305,19 +606,34 @@ impl Map {
             let name = object.name.clone();
             let x = object.x as f32 / 16.0;
             let y = -1.0 * object.y as f32 / 16.0 + 1.0;
-            let rectangle = Rectangle::new(
-                x,
-                y,
-                1.0 as f32,
-                1.0 as f32,
-            );
 
             let location = match object.properties.get("location") {
                 Some(tiled::PropertyValue::IntValue(val)) => *val,
                 _ => 0,
             } as u32;
 
-            doors.push(Door { rectangle, name, location });
+            let requires = Self::string_property(&object, "requires");
+
+            doors.push(Door { x, y, name, location, requires });
+        }
+    }
+
+    fn push_obstacles(obstacles: &mut Vec<Obstacle>, object_layer: &tiled::ObjectLayer) {
+        for object in object_layer.objects() {
+            let kind = match object.name.as_str() {
+                "Tree" => ObstacleKind::Tree,
+                "Boulder" => ObstacleKind::Boulder,
+                other => {
+                    println!("Unknown obstacle {}", other);
+                    continue;
+                }
+            };
+
+            let x = object.x as f32 / 16.0;
+            let y = -1.0 * object.y as f32 / 16.0 + 1.0;
+            let tile = object.tile_data().map_or(0, |tile| tile.id());
+
+            obstacles.push(Obstacle { kind, x, y, tile });
         }
     }
 
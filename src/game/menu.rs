@@ -1,11 +1,28 @@
 use cgmath;
+use crate::renderer::Renderer;
+use crate::assets;
 use crate::renderer::instance::Instance;
 use super::input_manager::InputManager;
 use winit::keyboard::KeyCode;
 use crate::game::Player;
 use crate::game::GameState;
 
+// screen pixels from the top left to the centre of the menu's first tile
+const MENU_X: f32 = 168.0;
+const MENU_Y: f32 = 8.0;
+// the pointer sits next to the first option and moves down a line per option
+const POINTER_X: f32 = 177.5;
+const POINTER_Y: f32 = 14.5;
+const LINE_HEIGHT: f32 = 15.0;
+const TILE_SIZE: f32 = 16.0;
+
+// screen space instance of one tile centred on a pixel position
+fn tile_model(x: f32, y: f32) -> [[f32; 4]; 4] {
+    (cgmath::Matrix4::from_translation(cgmath::Vector3::new(x, -y, 0.0)) * cgmath::Matrix4::from_scale(TILE_SIZE)).into()
+}
+
 pub enum MenuAction {
+    Pokedex,
     Pokemon,
     Save,
     Exit,
@@ -14,6 +31,10 @@ pub enum MenuAction {
 impl MenuAction {
     pub fn execute(&self, player: &Player) -> GameState {
         match self {
+            MenuAction::Pokedex => {
+                println!("Pokedex action");
+                GameState::Pokedex
+            },
             MenuAction::Pokemon => {
                 println!("Pokemon action");
                 GameState::Party
@@ -31,17 +52,17 @@ impl MenuAction {
 }
 
 pub struct Menu {
+    // drawn in screen space in the top right corner
     pub instances: Vec<Instance>,
-    pub offsets: Vec<cgmath::Vector3<f32>>,
     pub pointer: Instance,
     pub option: u32,
 }
 
 impl Menu {
-    pub fn new(loader: &mut tiled::Loader, player_position: cgmath::Vector3<f32>) -> Self {
-        let menu_loader = loader.load_tmx_map("/home/chris/games/SirSquare/assets/menu.tmx").unwrap();
+    pub fn new(loader: &mut tiled::Loader, renderer: &mut Renderer) -> Self {
+        let atlas_index = renderer.get_atlas("menu").unwrap().index;
+        let menu_loader = loader.load_tmx_map(assets::get().path("menu.tmx")).unwrap();
         let mut instances = Vec::new();
-        let mut offsets = Vec::new();
 
         for layer in menu_loader.layers() {
             if let tiled::LayerType::Tiles(tile_layer) = layer.layer_type() {
@@ -49,11 +70,10 @@ impl Menu {
                     for j in 0..tile_layer.height().unwrap() {
                         if let Some(tile) = tile_layer.get_tile(i as i32, j as i32) {
                             instances.push(Instance {
-                                model: cgmath::Matrix4::from_translation(cgmath::Vector3::new(i as f32, -1.0 * j as f32, 0.0)).into(),
+                                model: tile_model(MENU_X + i as f32 * TILE_SIZE, MENU_Y + j as f32 * TILE_SIZE),
                                 tex_index: tile.id() as u32,
-                                atlas_index: 2,
+                                atlas_index,
                             });
-                            offsets.push(cgmath::Vector3::new(i as f32, -1.0 * j as f32, 0.0));
                         }
                     }
                 }
@@ -61,14 +81,13 @@ impl Menu {
         }
 
         let pointer = Instance {
-            model: cgmath::Matrix4::from_translation(cgmath::Vector3::new(0.0, 0.0, 0.0)).into(),
+            model: tile_model(POINTER_X, POINTER_Y),
             tex_index: 55,
-            atlas_index: 2,
+            atlas_index,
         };
 
         Self {
             instances,
-            offsets,
             pointer,
             option: 0,
         }
@@ -76,9 +95,10 @@ impl Menu {
 
     fn get_action_for_option(option: u32) -> MenuAction {
         match option {
-            0 => MenuAction::Pokemon,
-            1 => MenuAction::Save,
-            2 => MenuAction::Exit,
+            0 => MenuAction::Pokedex,
+            1 => MenuAction::Pokemon,
+            2 => MenuAction::Save,
+            3 => MenuAction::Exit,
             _ => MenuAction::Exit,
         }
     }
@@ -86,19 +106,7 @@ impl Menu {
     pub fn update(&mut self, input_manager: &mut InputManager, player: &Player ) -> GameState {
         let release_key = input_manager.get_key_on_press();
 
-        for (i, offset) in self.offsets.iter().enumerate() {
-            let model = cgmath::Matrix4::from_translation(cgmath::Vector3::new(
-                player.position.x + offset.x + 3.0,
-                player.position.y + offset.y + 4.5,
-                offset.z)).into();
-            self.instances[i].model = model;
-        }
-
-        // Update menu pointer position
-        self.pointer.model = cgmath::Matrix4::from_translation(cgmath::Vector3::new(
-            player.position.x + 3.5 + 1.5/16.0,
-            player.position.y + 4.0 + 1.5/16.0 - self.option as f32 * 15.0/16.0,
-            0.0)).into();
+        self.pointer.model = tile_model(POINTER_X, POINTER_Y + self.option as f32 * LINE_HEIGHT);
 
         // Handle input
         if let Some(release_key) = release_key {
@@ -116,7 +124,7 @@ impl Menu {
                     }
                 },
                 KeyCode::KeyS => {
-                    if self.option < 2 { // Adjust this value based on the number of menu options
+                    if self.option < 3 { // Adjust this value based on the number of menu options
                         self.option += 1;
                     }
                 },
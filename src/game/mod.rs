@@ -13,17 +13,34 @@ mod battle;
 mod npc;
 mod entity;
 mod animation_player;
+mod trainer;
+mod dialogue;
+mod items;
+mod shop;
+mod trainer_card;
+mod script;
+mod pokedex;
+mod naming;
+mod field_moves;
+mod tile_grid;
+mod connections;
+mod transition;
+mod camera;
+#[cfg(test)]
+mod golden_tests;
 
 use std::time::{Instant, Duration};
 use player::Player;
 use input_manager::InputManager;
 use crate::renderer::Renderer;
+use crate::renderer::draw_list::{DrawList, Layer};
+use crate::renderer::instance::Instance;
 use tiled::Loader;
 use crate::game::gamestate::GameState;
 use crate::game::menu::Menu;
-use crate::game::battle::{Battle, BattleType};
+use crate::game::battle::{Battle, BattleType, BattleFormat, BattleOutcome};
 use pokemon::Pokemon;
-use party::Party;
+use party::{Party, PartyEvent};
 use crate::renderer::sprite::Sprite;
 use cgmath:: Vector3;
 use npc::NPC;
@@ -31,18 +48,46 @@ use entity::Entity;
 use animation_player::{Animation, AnimationSheet};
 use crate::audio::AudioPlayer;
 use rodio::OutputStream;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::path::PathBuf;
+use crate::options::Options;
+use crate::assets;
+use battle::replay::Replay;
+use dialogue::Dialogue;
+use shop::Shop;
+use items::Item;
+use trainer_card::TrainerCard;
+use script::Script;
+use pokedex::PokedexScreen;
+use naming::NamingScreen;
+use connections::Neighbor;
+use transition::{Fade, Transition};
+use camera::Camera;
+use crate::renderer::transition::Wipe;
+use std::collections::HashSet;
 
 pub enum Interaction {
     Heal,
     Battle(bool, Vec<Vector3<f32>>),
     Talk,
+    Shop(Vec<String>), // item names in stock
     None,
 }
 
+// where the player is sent back to after blacking out
+pub struct HealLocation {
+    pub map_name: String,
+    pub position: Vector3<f32>,
+    pub direction: Vector3<f32>,
+}
+
 pub struct Game {
     input_manager: InputManager,
     player: Player,
     map: map_loader::Map,
+    // maps joined to the current one by edge connections
+    neighbors: Vec<Neighbor>,
     foreground_animations: Vec<Animation>,
     state: GameState,
     time_of_last_update: Instant,
@@ -53,23 +98,50 @@ pub struct Game {
     debug_background: Sprite,
     npcs: Vec<NPC>,
     ground_animations: Vec<Animation>,
+    // map texture the ground and grass animations draw from, held so its layer isn't reused under them
+    animation_texture: String,
     queue_battle: (bool, (String, u32)),
     finished_battles: Vec<(String, u32)>, // (map_name, npc_id)
-    trainer: Option<(String, u32)>,
+    trainers: Vec<(String, u32)>,
     audio_player: AudioPlayer,
+    rng: StdRng,
+    record_dir: Option<PathBuf>,
+    replay_viewer: bool,
+    pub quit: bool,
+    dialogue: Option<Dialogue>,
+    heal_location: Option<HealLocation>,
+    blacked_out: bool,
+    shop: Option<Shop>,
+    prize_money: u32,
+    badge_reward: Option<String>,
+    // story progress that map objects can require, badges are stored as "<name> Badge"
+    flags: HashSet<String>,
+    trainer_card: Option<TrainerCard>,
+    play_time: Duration,
+    script: Option<Script>,
+    // set by cutscenes, hides the overworld until faded back in
+    faded: bool,
+    fade: Option<Fade>,
+    pokedex_screen: Option<PokedexScreen>,
+    naming: Option<NamingScreen>,
+    transition: Option<Transition>,
+    camera: Camera,
+    // boulders can be pushed until the next map change
+    strength: bool,
     //required to keep audio player alive
     #[allow(dead_code)]
-    stream: OutputStream,
+    stream: Option<OutputStream>,
 }
 
 impl Game {
-    pub async fn new(renderer: &mut Renderer) -> Self {
+    pub async fn new(renderer: &mut Renderer, options: &Options) -> Self {
 
         let mut loader = Loader::new();
-        let map_loader = loader.load_tmx_map("/home/chris/games/SirSquare/assets/landing.tmx").unwrap();
-        let map = map_loader::Map::new(&map_loader, 0, "landing".to_string());
+        let mut map = Self::read_map("landing", renderer);
+        map.upload(renderer);
 
         let mut ground_animations = Vec::new();
+        let landing_atlas = renderer.hold_texture("landing").unwrap().clone();
 
         for animated in &map.animated {
 
@@ -78,7 +150,7 @@ impl Game {
                 frame_height: 1,
                 frame_order: vec![0, 1, 2, 3],
                 frame_duration: Duration::from_millis(250),
-                atlas: renderer.get_atlas("landing").unwrap().clone(),
+                atlas: landing_atlas.clone(),
                 looped: true,
             };
 
@@ -88,7 +160,7 @@ impl Game {
             ground_animations.push(animation);
         }
 
-        let menu = Menu::new(&mut loader, cgmath::Vector3::new(0.0, 0.0, 0.0));
+        let menu = Menu::new(&mut loader, renderer);
 
         let mut player_pokemon = Vec::new();
 
@@ -100,7 +172,7 @@ impl Game {
 
         let mut npcs = Vec::new();
 
-        for npc in &map.npcs {
+        for npc in map.npcs.iter().filter(|npc| npc.present(&HashSet::new())) {
             let position = cgmath::Vector3::new(npc.x, npc.y, 0.0);
             let path = npc.path_id
                 .and_then(|path_id| map.paths.iter().find(|path| path.id == path_id).map(|p| p.points.clone()));
@@ -116,6 +188,7 @@ impl Game {
                     Interaction::Battle(false, battle_squares)
                 },
                 "Talk" => Interaction::Talk,
+                "Shop" => Interaction::Shop(npc.stock.clone()),
                 _ => Interaction::None,
             };
 
@@ -124,14 +197,29 @@ impl Game {
             npcs.push(new_npc);
         }
 
-        let (_stream, stream_handle) = OutputStream::try_default().unwrap();
+        // carry on silently without an audio device, as on a headless machine
+        let (_stream, stream_handle) = match OutputStream::try_default() {
+            Ok((stream, stream_handle)) => (Some(stream), Some(stream_handle)),
+            Err(e) => {
+                println!("No audio output, playing without sound: {:?}", e);
+                (None, None)
+            },
+        };
         let mut audio_player = AudioPlayer::new(stream_handle);
-        audio_player.play("/home/chris/games/SirSquare/assets/Pallet Town.mp3");
+        if let Some(path) = assets::get().map_music("landing") {
+            audio_player.play(&path.to_string_lossy());
+        }
 
-        Self {
+        let mut player = Player::new(renderer);
+        for pokemon in &player_pokemon {
+            player.pokedex.catch(pokemon.id);
+        }
+
+        let mut game = Self {
             input_manager: InputManager::new(),
-            player: Player::new(renderer),
+            player,
             map,
+            neighbors: Vec::new(),
             foreground_animations: Vec::new(),
             state: GameState::Running,
             time_of_last_update: Instant::now(),
@@ -142,12 +230,45 @@ impl Game {
             debug_background,
             npcs,
             ground_animations,
+            animation_texture: "landing".to_string(),
             queue_battle: (false, ("".to_string(), 0)),
             finished_battles: Vec::new(),
-            trainer: None,
+            trainers: Vec::new(),
             audio_player,
+            rng: StdRng::seed_from_u64(options.seed),
+            record_dir: options.record_dir.clone(),
+            replay_viewer: false,
+            quit: false,
+            dialogue: None,
+            heal_location: None,
+            blacked_out: false,
+            shop: None,
+            prize_money: 0,
+            badge_reward: None,
+            flags: HashSet::new(),
+            trainer_card: None,
+            play_time: Duration::ZERO,
+            script: None,
+            faded: false,
+            fade: None,
+            pokedex_screen: None,
+            naming: None,
+            transition: None,
+            camera: Camera::new(),
+            strength: false,
             stream: _stream,
+        };
+
+        game.load_connections(None, renderer);
+
+        if let Some(path) = &options.replay {
+            match Replay::load(path) {
+                Ok(replay) => game.start_replay(replay, renderer),
+                Err(e) => eprintln!("Failed to load replay {:?}: {:?}", path, e),
+            }
         }
+
+        game
     }
 
     pub fn input(&mut self, event: &winit::event::KeyEvent) {
@@ -158,6 +279,7 @@ impl Game {
         let now = Instant::now();
         let dt = now.duration_since(self.time_of_last_update);
         self.time_of_last_update = now;
+        self.play_time += dt;
 
         match self.state {
             GameState::Running => self.running(renderer, dt),
@@ -165,35 +287,155 @@ impl Game {
             GameState::Encounter => {
                 if let Some(encounter) = &mut self.encounter {
 
-                    if let Some(player_won) = encounter.update(&mut self.player_pokemon, &mut self.input_manager, dt, renderer) {
-                        if player_won {
-                            if encounter.battle_type == BattleType::Trainer {
-                                self.npc_defeated();
+                    if let Some(outcome) = encounter.update(&mut self.player_pokemon, &mut self.input_manager, dt, renderer) {
+                        if let Some(record_dir) = &self.record_dir {
+                            let path = record_dir.join(format!("battle_{}.replay", encounter.seed));
+                            let saved = std::fs::create_dir_all(record_dir).map_err(anyhow::Error::from)
+                                .and_then(|_| encounter.replay.save(&path));
+                            match saved {
+                                Ok(()) => println!("Saved replay to {:?}", path),
+                                Err(e) => eprintln!("Failed to save replay: {:?}", e),
                             }
-                        } else {
-                            self.load_map("pokecenter", 1, renderer);
-                            self.heal_pokemon();
                         }
 
+                        if self.replay_viewer {
+                            println!("Replay finished: {:?}", outcome);
+                            self.quit = true;
+                            return;
+                        }
+
+                        let caught = encounter.caught;
+                        if let Some(id) = caught {
+                            self.player.pokedex.catch(id);
+                        }
+
+                        let battle_type = encounter.battle_type;
                         self.encounter = None;
                         self.state = GameState::Running;
 
-                        match self.map.name.as_str() {
-                            "pokecenter" => self.audio_player.play("/home/chris/games/SirSquare/assets/Pokemon Center.mp3"),
-                            "gym" => self.audio_player.play("/home/chris/games/SirSquare/assets/Pokemon Gym.mp3"),
-                            _ => self.audio_player.play("/home/chris/games/SirSquare/assets/Pallet Town.mp3"),
+                        match outcome {
+                            BattleOutcome::Won if battle_type == BattleType::Trainer => self.npc_defeated(renderer),
+                            BattleOutcome::Lost => self.black_out(renderer),
+                            _ => {},
+                        }
+
+                        self.play_map_music();
+
+                        //caught pokemon join the end of the party
+                        if caught.is_some() && self.state == GameState::Running {
+                            self.start_naming(self.player_pokemon.len() - 1, false, renderer);
                         }
                     }
                 }
             },
             GameState::Party => {
-                if let Some(party) = &mut self.party {
-                    //6 is just the value returned when cancel is selected
-                    if party.update(&mut self.input_manager, dt, renderer) == 6 {
+                let event = match &mut self.party {
+                    Some(party) => party.update(&self.player_pokemon, &mut self.input_manager, dt, renderer),
+                    None => PartyEvent::None,
+                };
+
+                match event {
+                    PartyEvent::Cancel => self.state = GameState::Paused,
+                    PartyEvent::Switch(a, b) => {
+                        self.player_pokemon.swap(a, b);
+                        self.enter_party(renderer);
+                    },
+                    PartyEvent::UseItem { slot, item } => {
+                        let used = Item::new(&item);
+                        if used.use_on(&mut self.player_pokemon[slot]) && used.consumed() {
+                            self.player.inventory.remove(&item, 1);
+                            println!("Used {} on {}", item, self.player_pokemon[slot].display_name());
+                        }
+                        self.enter_party(renderer);
+                    },
+                    PartyEvent::GiveItem { slot, item } => {
+                        if self.player.inventory.remove(&item, 1) {
+                            let pokemon = &mut self.player_pokemon[slot];
+                            //whatever it held before goes back in the bag
+                            if let Some(previous) = pokemon.held_item.replace(item) {
+                                self.player.inventory.add(&previous, 1);
+                            }
+                            println!("{} is now holding {:?}", pokemon.display_name(), pokemon.held_item);
+                        }
+                        self.enter_party(renderer);
+                    },
+                    PartyEvent::TakeItem(slot) => {
+                        if let Some(item) = self.player_pokemon[slot].held_item.take() {
+                            println!("Took {} from {}", item, self.player_pokemon[slot].display_name());
+                            self.player.inventory.add(&item, 1);
+                        }
+                        self.enter_party(renderer);
+                    },
+                    PartyEvent::FieldMove { slot, field_move } => self.use_field_move_from_party(slot, field_move, renderer),
+                    PartyEvent::Rename(slot) => self.start_naming(slot, true, renderer),
+                    PartyEvent::Selected(_) | PartyEvent::None => {},
+                }
+            },
+            GameState::Naming => {
+                let done = match &mut self.naming {
+                    Some(naming) => naming.update(&mut self.input_manager, renderer),
+                    None => false,
+                };
+
+                if done {
+                    if let Some(naming) = self.naming.take() {
+                        let pokemon = &mut self.player_pokemon[naming.slot];
+                        //an empty name or the species name clears the nickname
+                        pokemon.nickname = if naming.name.is_empty() || naming.name == pokemon.species {
+                            None
+                        } else {
+                            Some(naming.name)
+                        };
+                        println!("Named {} {}", pokemon.species, pokemon.display_name());
+
+                        if naming.from_party {
+                            self.enter_party(renderer);
+                        } else {
+                            self.state = GameState::Running;
+                        }
+                    }
+                }
+            },
+            GameState::Dialogue => {
+                self.update_camera(renderer, dt);
+
+                if let Some(dialogue) = &mut self.dialogue {
+                    if dialogue.update(&mut self.input_manager, renderer) {
+                        self.dialogue = None;
+                        self.blacked_out = false;
+                        self.state = GameState::Running;
+                    }
+                }
+            },
+            GameState::Shop => {
+                self.update_camera(renderer, dt);
+
+                if let Some(shop) = &mut self.shop {
+                    if shop.update(&mut self.input_manager, &mut self.player, renderer) {
+                        self.shop = None;
+                        self.state = GameState::Running;
+                    }
+                }
+            },
+            GameState::Pokedex => {
+                if let Some(pokedex_screen) = &mut self.pokedex_screen {
+                    if pokedex_screen.update(&self.player.pokedex, &mut self.input_manager, renderer) {
+                        self.pokedex_screen = None;
                         self.state = GameState::Paused;
                     }
                 }
             },
+            GameState::TrainerCard => {
+                self.update_camera(renderer, dt);
+
+                if let Some(trainer_card) = &mut self.trainer_card {
+                    if trainer_card.update(&mut self.input_manager) {
+                        self.trainer_card = None;
+                        self.state = GameState::Running;
+                    }
+                }
+            },
+            GameState::Transition => self.update_transition(renderer, dt),
             GameState::Debug => {
 
             },
@@ -201,35 +443,79 @@ impl Game {
     }
 
     pub fn draw(&mut self, renderer: &mut Renderer) {
-        match self.state {
-            GameState::Running | GameState::Paused => {
-                let mut instances = Vec::new();
-                instances.extend_from_slice(&self.map.background);
-                instances.extend_from_slice(&self.map.ground);
-
-                instances.extend(self.ground_animations.iter().flat_map(|animation| &animation.instances));
-
-                instances.extend_from_slice(&self.map.foreground);
-
-                let mut entities: Vec<&dyn Entity> = Vec::new();
-                entities.push(&self.player);
-                entities.extend(self.npcs.iter().map(|npc| npc as &dyn Entity));
-                entities.extend(self.foreground_animations.iter().map(|animation| animation as &dyn Entity));
-
-                entities.sort_by(|a, b| b.position().y.partial_cmp(&a.position().y).unwrap_or(std::cmp::Ordering::Equal));
+        //while a transition plays the scene underneath it is drawn
+        let state = match &self.transition {
+            Some(transition) if self.state == GameState::Transition => {
+                renderer.set_wipe(transition.wipe, transition.progress());
+                transition.scene
+            },
+            _ => {
+                match &self.fade {
+                    Some(fade) => renderer.set_wipe(Wipe::FadeBlack, fade.progress()),
+                    None => renderer.set_wipe(Wipe::None, 0.0),
+                }
+                self.state
+            },
+        };
 
-                for entity in entities {
-                    instances.extend_from_slice(entity.instances());
+        match state {
+            GameState::Running | GameState::Dialogue if self.blacked_out || self.faded => {
+                let instances = self.dialogue.as_ref().map_or(Vec::new(), |dialogue| dialogue.instances());
+                let _ = renderer.render(&instances, false);
+            },
+            GameState::Running | GameState::Paused | GameState::Dialogue | GameState::Shop | GameState::TrainerCard => {
+                //tile layers are already on the gpu, only what moves is collected each frame
+                let ground_animations: Vec<Instance> = self.ground_animations.iter()
+                    .flat_map(|animation| animation.instances.iter().copied())
+                    .collect();
+
+                let obstacles = self.map.obstacle_instances(self.map.atlas_index);
+                let shadow: Vec<Instance> = self.player.shadow().into_iter().collect();
+
+                let mut list = DrawList::new();
+                for neighbor in &self.neighbors {
+                    list.push_static(Layer::Map, &neighbor.map.layers.background);
+                    list.push_static(Layer::Map, &neighbor.map.layers.ground);
+                    list.push_static(Layer::Map, &neighbor.map.layers.foreground);
+                }
+                list.push_static(Layer::Map, &self.map.layers.background);
+                list.push_static(Layer::Map, &self.map.layers.ground);
+                list.push(Layer::Map, &ground_animations);
+                list.push_static(Layer::Map, &self.map.layers.foreground);
+                list.push(Layer::Map, &shadow);
+
+                //obstacles go first so an entity on the same row is drawn over them
+                for obstacle in &obstacles {
+                    list.push(Layer::Entities, std::slice::from_ref(obstacle));
+                }
+                list.push_sorted(Layer::Entities, self.player.position.y, self.player.instances());
+                for npc in &self.npcs {
+                    list.push_sorted(Layer::Entities, npc.position.y, npc.instances());
+                }
+                for animation in &self.foreground_animations {
+                    list.push_sorted(Layer::Entities, animation.position().y, animation.instances());
                 }
 
-                instances.extend_from_slice(&self.map.aboveground);
+                list.push_static(Layer::Overlay, &self.map.layers.aboveground);
+                for neighbor in &self.neighbors {
+                    list.push_static(Layer::Overlay, &neighbor.map.layers.aboveground);
+                }
 
-                if self.state == GameState::Paused {
-                    instances.extend_from_slice(&self.menu.instances);
-                    instances.push(self.menu.pointer);
+                if state == GameState::Paused {
+                    list.push(Layer::Ui, &self.menu.instances);
+                    list.push(Layer::Ui, std::slice::from_ref(&self.menu.pointer));
+                }
+                if let Some(dialogue) = &self.dialogue {
+                    list.push(Layer::Ui, &dialogue.instances());
+                }
+                if let Some(shop) = &self.shop {
+                    list.push(Layer::Ui, &shop.instances());
+                }
+                if let Some(trainer_card) = &self.trainer_card {
+                    list.push(Layer::Ui, &trainer_card.instances());
                 }
 
-                let _ = renderer.render(&instances, true);
+                let _ = renderer.draw(list);
             },
             GameState::Encounter => {
                 if let Some(encounter) = &self.encounter {
@@ -241,6 +527,17 @@ impl Game {
                     party.draw(renderer);
                 }
             },
+            GameState::Pokedex => {
+                if let Some(pokedex_screen) = &self.pokedex_screen {
+                    pokedex_screen.draw(renderer);
+                }
+            },
+            GameState::Naming => {
+                if let Some(naming) = &self.naming {
+                    naming.draw(renderer);
+                }
+            },
+            GameState::Transition => {},
             GameState::Debug => {
                 let mut instances = Vec::new();
 
@@ -251,43 +548,164 @@ impl Game {
         }
     }
 
-    pub fn start_encounter(&mut self, pokemon: Pokemon, renderer: &mut Renderer) {
+    pub fn start_encounter(&mut self, mut pokemon: Pokemon, renderer: &mut Renderer) {
+        //wild pokemon carry their common item half the time and their rare one 5% of the time
+        let (common, rare) = pokemon::wild_held_items(&pokemon.species);
+        pokemon.held_item = match self.rng.gen_range(0..100) {
+            0..=4 => rare,
+            5..=54 => common,
+            _ => None,
+        }.map(str::to_string);
+
         let enemy_pokemon = vec![pokemon.clone()];
 
-        self.encounter = Some(Battle::new(BattleType::Wild, &mut self.player_pokemon, enemy_pokemon, renderer));
+        let seed = self.rng.gen();
+        let battle = Battle::new(BattleType::Wild, BattleFormat::Single, &mut self.player_pokemon, enemy_pokemon, vec![0], seed, renderer);
+        battle.seen.iter().for_each(|&id| self.player.pokedex.see(id));
+        self.encounter = Some(battle);
         self.state = GameState::Encounter;
-        self.audio_player.play("/home/chris/games/SirSquare/assets/Wild Battle.mp3");
+        self.play_music("wild_battle");
     }
 
-    pub fn start_battle(&mut self, npc_id: (String, u32), pokemon: Vec<Pokemon>, renderer: &mut Renderer) {
-        self.encounter = Some(Battle::new(BattleType::Trainer, &mut self.player_pokemon, pokemon, renderer));
+    pub fn start_replay(&mut self, replay: Replay, renderer: &mut Renderer) {
+        let build_team = |team: &Vec<battle::replay::TeamMember>, renderer: &mut Renderer| -> Vec<Pokemon> {
+            team.iter().map(|member| member.to_pokemon(renderer)).collect()
+        };
+
+        self.player_pokemon = build_team(&replay.player_team, renderer);
+        let enemy_pokemon = build_team(&replay.enemy_team, renderer);
+
+        let enemy_owners = replay.enemy_team.iter().map(|member| member.owner).collect();
+
+        let mut battle = Battle::new(replay.battle_type, replay.format, &mut self.player_pokemon, enemy_pokemon, enemy_owners, replay.seed, renderer);
+        battle.play_back(replay.actions);
+
+        self.encounter = Some(battle);
         self.state = GameState::Encounter;
+        self.replay_viewer = true;
+        // don't overwrite the file being viewed
+        self.record_dir = None;
+    }
 
-        if npc_id == ("gym".to_string(), 5) {
-            self.audio_player.play("/home/chris/games/SirSquare/assets/Gym Battle.mp3");
+    pub fn start_battle(&mut self, npc_id: (String, u32), renderer: &mut Renderer) {
+        let trainer = trainer::generate_trainer(&npc_id.0, npc_id.1, renderer);
+
+        let mut format = trainer.format;
+        let mut enemy_owners = vec![0; trainer.pokemon.len()];
+        let mut enemy_pokemon = trainer.pokemon;
+        let mut trainers = vec![npc_id.clone()];
+        let mut prize_money = trainer.prize;
+        let is_leader = trainer.badge.is_some();
+        self.badge_reward = trainer.badge;
+
+        if let Some(partner_id) = trainer.partner {
+            let partner = trainer::generate_trainer(&npc_id.0, partner_id, renderer);
+            enemy_owners.extend(vec![1; partner.pokemon.len()]);
+            enemy_pokemon.extend(partner.pokemon);
+            prize_money += partner.prize;
+            trainers.push((npc_id.0.clone(), partner_id));
+            format = BattleFormat::Tag;
+        }
+
+        let seed = self.rng.gen();
+        let battle = Battle::new(BattleType::Trainer, format, &mut self.player_pokemon, enemy_pokemon, enemy_owners, seed, renderer);
+        battle.seen.iter().for_each(|&id| self.player.pokedex.see(id));
+        self.encounter = Some(battle);
+        self.state = GameState::Encounter;
+
+        if is_leader {
+            self.play_music("gym_battle");
         } else {
-            self.audio_player.play("/home/chris/games/SirSquare/assets/Trainer Battle.mp3");
+            self.play_music("trainer_battle");
+        }
+
+        self.trainers = trainers;
+        self.prize_money = prize_money;
+    }
+
+    pub fn show_dialogue(&mut self, messages: &[&str], renderer: &mut Renderer) {
+        self.dialogue = Some(Dialogue::new(messages, renderer));
+        self.state = GameState::Dialogue;
+    }
+
+    fn black_out(&mut self, renderer: &mut Renderer) {
+        self.script = None;
+        self.faded = false;
+        self.fade = None;
+
+        let lost = self.player.money / 2;
+        self.player.money -= lost;
+
+        match &self.heal_location {
+            Some(location) => {
+                let (map_name, position, direction) = (location.map_name.clone(), location.position, location.direction);
+                self.load_map(&map_name, 0, renderer);
+                self.player.position = position;
+                self.player.target_position = position;
+                self.player.orient(direction);
+            },
+            None => {
+                self.load_map("pokecenter", 1, renderer);
+            },
         }
+        self.heal_pokemon();
 
-        self.trainer = Some(npc_id);
+        self.blacked_out = true;
+        let lost_message = format!("You dropped {} money in your panic.", lost);
+        self.show_dialogue(&["You are out of usable Pokemon.", &lost_message, "You scurried back to the last Pokemon Center you used."], renderer);
     }
 
-    pub fn npc_defeated(&mut self) {
+    pub fn npc_defeated(&mut self, renderer: &mut Renderer) {
         println!("NPC defeated");
-        let npc_id = self.trainer.clone().unwrap();
-        println!("{:?}", npc_id);
 
-        if let Some(npc) = self.npcs.iter_mut().find(|npc| npc.id == npc_id) {
-            if let Interaction::Battle(ref mut battled, _) = npc.interaction {
-                *battled = true;
+        let prize_money = std::mem::take(&mut self.prize_money);
+        self.player.money += prize_money;
+        let mut messages = vec![format!("You got {} money for winning.", prize_money)];
+
+        if let Some(badge) = self.badge_reward.take() {
+            println!("Awarded {} Badge", badge);
+            messages.push(format!("You received the {} Badge.", badge));
+            self.flags.insert(format!("{} Badge", badge));
+            self.player.badges.push(badge);
+        }
+
+        let messages: Vec<&str> = messages.iter().map(|message| message.as_str()).collect();
+        self.show_dialogue(&messages, renderer);
+
+        for npc_id in std::mem::take(&mut self.trainers) {
+            println!("{:?}", npc_id);
+
+            if let Some(npc) = self.npcs.iter_mut().find(|npc| npc.id == npc_id) {
+                if let Interaction::Battle(ref mut battled, _) = npc.interaction {
+                    *battled = true;
+                }
             }
+            self.finished_battles.push(npc_id);
         }
-        self.finished_battles.push(npc_id);
-        self.trainer = None;
+    }
+
+    pub fn open_shop(&mut self, stock: &[String], renderer: &mut Renderer) {
+        self.shop = Some(Shop::new(stock, &self.player, renderer));
+        self.state = GameState::Shop;
+    }
+
+    pub fn open_trainer_card(&mut self, renderer: &mut Renderer) {
+        let pokedex = &self.player.pokedex;
+        self.trainer_card = Some(TrainerCard::new(self.player.money, self.play_time, pokedex.seen.len(), pokedex.caught.len(), &self.player.badges, renderer));
+        self.state = GameState::TrainerCard;
+    }
+
+    pub fn start_naming(&mut self, slot: usize, from_party: bool, renderer: &mut Renderer) {
+        let pokemon = &self.player_pokemon[slot];
+        let current = pokemon.nickname.clone().unwrap_or_default();
+        self.naming = Some(NamingScreen::new(&pokemon.species, &current, slot, from_party, renderer));
+        self.state = GameState::Naming;
     }
 
     pub fn enter_party(&mut self, renderer: &mut Renderer) {
-        self.party = Some(Party::new(&mut self.player_pokemon, false, false, renderer));
+        let mut party = Party::new(&mut self.player_pokemon, false, false, renderer);
+        party.items = self.player.inventory.items.clone();
+        self.party = Some(party);
         self.state = GameState::Party;
     }
 }
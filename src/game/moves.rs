@@ -1,3 +1,5 @@
+use super::pokemon::Status;
+
 #[derive(Clone)]
 pub enum AttackType {
     Physical,
@@ -5,7 +7,7 @@ pub enum AttackType {
     Status,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Type {
     Normal,
     Fire,
@@ -24,6 +26,35 @@ pub enum Type {
     Dragon,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum Target {
+    Single,
+    AllFoes,
+    User,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Stat {
+    Attack,
+    Defense,
+    Speed,
+    SpecialAttack,
+    SpecialDefense,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MoveEffect {
+    StatChange {
+        stat: Stat,
+        stages: i32,
+    },
+    Inflict {
+        status: Status,
+    },
+    // Whirlwind: ends wild battles, drags in a random benched pokemon otherwise
+    ForceSwitch,
+}
+
 #[derive(Clone)]
 pub struct Move {
     pub name: String,
@@ -31,7 +62,12 @@ pub struct Move {
     pub power: u32,
     pub accuracy: u32,
     pub pp: u32,
+    // uses left, refilled when the party is healed
+    pub current_pp: u32,
     pub attack_type: AttackType,
+    pub target: Target,
+    pub priority: i32,
+    pub effect: Option<MoveEffect>,
 }
 
 impl Move {
@@ -73,6 +109,7 @@ impl Move {
             "Earthquake" => (Type::Ground, 100, 100, 10, AttackType::Physical),
             "Rock Slide" => (Type::Rock, 75, 90, 10, AttackType::Physical),
             "Strength" => (Type::Normal, 80, 100, 15, AttackType::Physical),
+            "Cut" => (Type::Normal, 50, 95, 30, AttackType::Physical),
             "Double-Edge" => (Type::Normal, 120, 100, 15, AttackType::Physical),
             "Wrap" => (Type::Normal, 15, 90, 20, AttackType::Physical),
             "Dragon Rage" => (Type::Dragon, 0, 100, 10, AttackType::Special),
@@ -86,17 +123,47 @@ impl Move {
             "Minimize" => (Type::Normal, 0, 100, 20, AttackType::Status),
             "Flash" => (Type::Normal, 0, 70, 20, AttackType::Status),
             "Thunder" => (Type::Electric, 110, 70, 10, AttackType::Special),
+            "Whirlwind" => (Type::Normal, 0, 100, 20, AttackType::Status),
+            "Struggle" => (Type::Normal, 50, 100, 1, AttackType::Physical),
 
             _ => (Type::Normal, 0, 0, 0, AttackType::Status), // Default case for unknown moves
         };
 
+        let target = match name {
+            "Growl" | "Tail Whip" | "Surf" | "Earthquake" | "Blizzard" | "Rock Slide" => Target::AllFoes,
+            "Withdraw" | "Harden" | "Minimize" | "Agility" => Target::User,
+            _ => Target::Single,
+        };
+
+        let priority = match name {
+            "Quick Attack" => 1,
+            "Whirlwind" => -6,
+            _ => 0,
+        };
+
+        let effect = match name {
+            "Growl" => Some(MoveEffect::StatChange { stat: Stat::Attack, stages: -1 }),
+            "Tail Whip" => Some(MoveEffect::StatChange { stat: Stat::Defense, stages: -1 }),
+            "Withdraw" | "Harden" => Some(MoveEffect::StatChange { stat: Stat::Defense, stages: 1 }),
+            "Agility" => Some(MoveEffect::StatChange { stat: Stat::Speed, stages: 2 }),
+            "Thunder Wave" | "Stun Spore" => Some(MoveEffect::Inflict { status: Status::Paralysis }),
+            "Poison Powder" => Some(MoveEffect::Inflict { status: Status::Poison }),
+            "Sleep Powder" | "Sing" => Some(MoveEffect::Inflict { status: Status::Sleep }),
+            "Whirlwind" => Some(MoveEffect::ForceSwitch),
+            _ => None,
+        };
+
         Self {
             name: name.to_string(),
             move_type,
             power,
             accuracy,
             pp,
+            current_pp: pp,
             attack_type,
+            target,
+            priority,
+            effect,
         }
     }
 }
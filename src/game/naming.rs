@@ -0,0 +1,112 @@
+use winit::keyboard::KeyCode;
+use crate::game::font::Font;
+use crate::game::input_manager::InputManager;
+use crate::renderer::Renderer;
+use crate::renderer::sprite::Sprite;
+
+const MAX_LENGTH: usize = 10;
+// every character the font can draw, laid out as the on screen keyboard
+const KEYBOARD: [&str; 7] = [
+    "ABCDEFGHIJ",
+    "KLMNOPQRST",
+    "UVWXYZ .,-",
+    "abcdefghij",
+    "klmnopqrst",
+    "uvwxyz'!?:",
+    "0123456789",
+];
+
+pub struct NamingScreen {
+    pub name: String,
+    // party slot of the pokemon being named
+    pub slot: usize,
+    // opened from the summary rather than after a catch
+    pub from_party: bool,
+    column: usize,
+    row: usize,
+    top_box: Sprite,
+    selector: Sprite,
+    title: Font,
+    hint: Font,
+    name_text: Font,
+    keyboard: Vec<Font>,
+}
+
+impl NamingScreen {
+    pub fn new(species: &str, current: &str, slot: usize, from_party: bool, renderer: &mut Renderer) -> Self {
+        let top_box = renderer.create_sprite(0.0, 0.0, 0, 7, 15, 3, "battle", 1.0, 1.0).expect("");
+        let selector = renderer.create_sprite(19.0, 50.0, 13, 10, 1, 1, "battle", 1.0, 1.0).expect("");
+        let title = Font::new(14.0, 11.0, &format!("Nickname for {}?", species.to_uppercase()), true, "white_font", renderer);
+        let hint = Font::new(226.0, 26.0, "Enter to finish", false, "white_font", renderer);
+
+        let keyboard = KEYBOARD.iter().enumerate().flat_map(|(row, keys)| {
+            keys.chars().enumerate().map(move |(column, key)| (row, column, key))
+        })
+            .map(|(row, column, key)| Font::new(30.0 + column as f32 * 20.0, 54.0 + row as f32 * 14.0, &key.to_string(), true, "white_font", renderer))
+            .collect();
+
+        let name = current.to_string();
+        let name_text = Font::new(14.0, 26.0, &name, true, "white_font", renderer);
+
+        Self {
+            name,
+            slot,
+            from_party,
+            column: 0,
+            row: 0,
+            top_box,
+            selector,
+            title,
+            hint,
+            name_text,
+            keyboard,
+        }
+    }
+
+    /// Returns true once the name is confirmed with Enter
+    pub fn update(&mut self, input_manager: &mut InputManager, renderer: &mut Renderer) -> bool {
+        let Some(key) = input_manager.get_key_on_press() else {
+            return false;
+        };
+
+        let columns = KEYBOARD[0].len();
+
+        match key {
+            KeyCode::KeyW => self.row = (self.row + KEYBOARD.len() - 1) % KEYBOARD.len(),
+            KeyCode::KeyS => self.row = (self.row + 1) % KEYBOARD.len(),
+            KeyCode::KeyA => self.column = (self.column + columns - 1) % columns,
+            KeyCode::KeyD => self.column = (self.column + 1) % columns,
+            KeyCode::KeyZ => {
+                if self.name.chars().count() < MAX_LENGTH {
+                    self.name.extend(KEYBOARD[self.row].chars().nth(self.column));
+                }
+            },
+            KeyCode::KeyX => {
+                self.name.pop();
+            },
+            KeyCode::Enter => return true,
+            _ => {},
+        }
+
+        self.selector.update_position(19.0 + self.column as f32 * 20.0, 50.0 + self.row as f32 * 14.0);
+        self.name_text = Font::new(14.0, 26.0, &self.name, true, "white_font", renderer);
+
+        false
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer) {
+        let mut instances = Vec::new();
+
+        instances.extend_from_slice(&self.top_box.texture);
+        instances.extend_from_slice(&self.selector.texture);
+        instances.extend_from_slice(&self.title.instanced());
+        instances.extend_from_slice(&self.hint.instanced());
+        instances.extend_from_slice(&self.name_text.instanced());
+
+        for key in &self.keyboard {
+            instances.extend_from_slice(&key.instanced());
+        }
+
+        let _ = renderer.render(&instances, false);
+    }
+}
@@ -6,7 +6,6 @@ use crate::game::animation_player::{Animation, AnimationSheet, AnimationPlayer};
 use std::collections::HashMap;
 use std::time::Duration;
 use crate::game::Interaction;
-use crate::game::pokemon::Pokemon;
 
 const ANIMATION_DURATION_WALKING: Duration = Duration::from_millis(125); // Duration to switch animation frames
 const MOVEMENT_DURATION_WALKING: Duration = Duration::from_millis(250); // Duration to move from one grid cell to another
@@ -187,22 +186,16 @@ impl NPC {
         self.next_point = next;
     }
 
-}
-
-pub fn generate_pokemon(name: String, id: u32, renderer: &mut Renderer) -> Vec<Pokemon> {
-
-    let pokemon = match (name.as_str(), id) {
-        ("gym", 5) => vec![Pokemon::new("Staryu".to_string(), 18, renderer),
-                            Pokemon::new("Starmie".to_string(), 21, renderer)],
-        ("gym", 4) => vec![Pokemon::new("Goldeen".to_string(), 19, renderer)],
-        ("gym", 3) => vec![
-            Pokemon::new("Horsea".to_string(), 16, renderer),
-            Pokemon::new("Shellder".to_string(), 16, renderer)
-        ],
-        _ => vec![Pokemon::new("Charizard".to_string(), 5, renderer)],
-    };
-
-    pokemon
+    pub fn face(&mut self, direction: Vector3<f32>) {
+        self.direction = direction;
+        self.animation_player.current_animation = match direction {
+            Vector3 { x: 0.0, y: 1.0, z: 0.0 } => "up",
+            Vector3 { x: -1.0, y: 0.0, z: 0.0 } => "left",
+            Vector3 { x: 1.0, y: 0.0, z: 0.0 } => "right",
+            _ => "down",
+        }.to_string();
+        // idle npcs don't update, so move the new animation's frames into place
+        self.animation_player.update(self.position, Duration::ZERO);
+    }
 
 }
-
@@ -1,8 +1,13 @@
 mod slot;
 mod cancel;
+mod summary;
 
 use cancel::Cancel;
 use slot::Slot;
+use summary::Summary;
+use crate::game::font::Font;
+use crate::game::items::Item;
+use crate::game::field_moves::FieldMove;
 use crate::renderer::Renderer;
 use crate::game::pokemon::Pokemon;
 use crate::game::input_manager::InputManager;
@@ -13,6 +18,45 @@ use winit::keyboard::KeyCode;
 
 const HOLD_DURATION: Duration = Duration::from_millis(500);
 const HOLD_TIME_PER_SLOT: Duration = Duration::from_millis(100);
+const SUBMENU_OPTIONS: [&str; 4] = ["Summary", "Switch", "Item", "Cancel"];
+const ITEM_OPTIONS: [&str; 4] = ["Use", "Give", "Take", "Cancel"];
+// options are laid out in rows of four, field moves go on the second row
+const OPTIONS_PER_ROW: usize = 4;
+
+pub enum PartyEvent {
+    None,
+    Cancel,
+    // a slot was picked while in battle
+    Selected(usize),
+    Switch(usize, usize),
+    // open the naming screen from the summary
+    Rename(usize),
+    UseItem {
+        slot: usize,
+        item: String,
+    },
+    GiveItem {
+        slot: usize,
+        item: String,
+    },
+    TakeItem(usize),
+    FieldMove {
+        slot: usize,
+        field_move: FieldMove,
+    },
+}
+
+#[derive(Clone, Copy)]
+enum PartyMode {
+    Choosing,
+    Submenu,
+    // waiting for the slot to swap the given one with
+    Switching(usize),
+    ItemActions,
+    // picking from the bag, either to use or to give
+    Items { give: bool },
+    Summary,
+}
 
 pub struct Party {
     background: Sprite,
@@ -25,6 +69,17 @@ pub struct Party {
     processed_initial_press: bool,
     in_battle: bool,
     required: bool,
+    mode: PartyMode,
+    menu_box: Sprite,
+    selector: Sprite,
+    submenu_selection: usize,
+    submenu_options: Vec<&'static str>,
+    submenu_text: Vec<Font>,
+    message: Option<&'static str>,
+    summary: Option<Summary>,
+    // the player's bag, (name, count)
+    pub items: Vec<(String, u32)>,
+    item_selection: usize,
 }
 
 impl Party {
@@ -45,6 +100,9 @@ impl Party {
         let time_of_hold = Instant::now();
         let processed_initial_press = false;
 
+        let menu_box = renderer.create_sprite(0.0, 7.0 * 16.0, 0, 7, 15, 3, "battle", 1.0, 1.0).expect("");
+        let selector = renderer.create_sprite(3.0, 119.0, 13, 10, 1, 1, "battle", 1.0, 1.0).expect("");
+
         Self {
             background,
             slots,
@@ -56,14 +114,29 @@ impl Party {
             processed_initial_press,
             in_battle,
             required,
+            mode: PartyMode::Choosing,
+            menu_box,
+            selector,
+            submenu_selection: 0,
+            submenu_options: SUBMENU_OPTIONS.to_vec(),
+            submenu_text: Vec::new(),
+            message: None,
+            summary: None,
+            items: Vec::new(),
+            item_selection: 0,
         }
     }
 
-    pub fn update(&mut self, input_manager: &mut InputManager, dt: Duration, renderer: &mut Renderer) -> u32 {
+    pub fn update(&mut self, pokemon: &[Pokemon], input_manager: &mut InputManager, dt: Duration, renderer: &mut Renderer) -> PartyEvent {
 
         let input_key = input_manager.get_last_key();
         let single_press_key = input_manager.get_key_on_press();
 
+        match self.mode {
+            PartyMode::Choosing | PartyMode::Switching(_) => {},
+            _ => return self.update_submenu(pokemon, single_press_key, renderer),
+        }
+
         if input_key != self.last_key {
             self.last_key = input_key;
             self.time_of_input = Instant::now();
@@ -80,16 +153,42 @@ impl Party {
                 self.handle_key_press(time_held, 1);
             }
             Some(KeyCode::KeyX) => {
-                self.selected_slot = self.slots.len() as i32;
+                if let PartyMode::Switching(_) = self.mode {
+                    self.close_submenu();
+                } else {
+                    self.selected_slot = self.slots.len() as i32;
+                }
             }
             _ => {}
         }
 
         if single_press_key == Some(KeyCode::KeyZ) {
-            if self.selected_slot == self.slots.len() as i32 && !self.required {
-                return 6;
-            } else {
-                return self.selected_slot as u32;
+            let on_cancel = self.selected_slot == self.slots.len() as i32;
+
+            match self.mode {
+                PartyMode::Switching(from) => {
+                    self.close_submenu();
+                    if !on_cancel && from != self.selected_slot as usize {
+                        return PartyEvent::Switch(from, self.selected_slot as usize);
+                    }
+                },
+                _ if on_cancel => {
+                    if !self.required {
+                        return PartyEvent::Cancel;
+                    }
+                },
+                _ if self.in_battle => return PartyEvent::Selected(self.selected_slot as usize),
+                _ => {
+                    //field moves the pokemon knows can be used from here
+                    let field_moves = pokemon[self.selected_slot as usize].moves.iter()
+                        .filter_map(|known| FieldMove::from_name(&known.name))
+                        .map(FieldMove::name);
+                    self.submenu_options = SUBMENU_OPTIONS.iter().copied().chain(field_moves).collect();
+
+                    self.mode = PartyMode::Submenu;
+                    self.submenu_selection = 0;
+                    self.refresh_submenu(renderer);
+                },
             }
         }
 
@@ -105,7 +204,152 @@ impl Party {
             self.cancel.selected = true;
         }
 
-        return 7;
+        return PartyEvent::None;
+    }
+
+    fn update_submenu(&mut self, pokemon: &[Pokemon], key: Option<KeyCode>, renderer: &mut Renderer) -> PartyEvent {
+        let Some(key) = key else {
+            return PartyEvent::None;
+        };
+        let slot = self.selected_slot as usize;
+
+        match self.mode {
+            PartyMode::Submenu => match key {
+                KeyCode::KeyA if self.submenu_selection > 0 => self.submenu_selection -= 1,
+                KeyCode::KeyD if self.submenu_selection < self.submenu_options.len() - 1 => self.submenu_selection += 1,
+                KeyCode::KeyW if self.submenu_selection >= OPTIONS_PER_ROW => self.submenu_selection -= OPTIONS_PER_ROW,
+                KeyCode::KeyS if self.submenu_selection + OPTIONS_PER_ROW < self.submenu_options.len() => self.submenu_selection += OPTIONS_PER_ROW,
+                KeyCode::KeyX => self.close_submenu(),
+                KeyCode::KeyZ => match self.submenu_options[self.submenu_selection] {
+                    "Summary" => {
+                        self.summary = Some(Summary::new(&pokemon[slot], renderer));
+                        self.mode = PartyMode::Summary;
+                    },
+                    "Switch" => self.mode = PartyMode::Switching(slot),
+                    "Item" => {
+                        self.submenu_selection = 0;
+                        self.mode = PartyMode::ItemActions;
+                    },
+                    "Cancel" => self.close_submenu(),
+                    name => {
+                        self.close_submenu();
+                        if let Some(field_move) = FieldMove::from_name(name) {
+                            return PartyEvent::FieldMove { slot, field_move };
+                        }
+                    },
+                },
+                _ => {},
+            },
+            PartyMode::ItemActions => match key {
+                KeyCode::KeyA if self.submenu_selection > 0 => self.submenu_selection -= 1,
+                KeyCode::KeyD if self.submenu_selection < ITEM_OPTIONS.len() - 1 => self.submenu_selection += 1,
+                KeyCode::KeyX => {
+                    self.submenu_selection = 2;
+                    self.mode = PartyMode::Submenu;
+                },
+                KeyCode::KeyZ => match ITEM_OPTIONS[self.submenu_selection] {
+                    "Use" | "Give" => {
+                        let give = self.submenu_selection == 1;
+                        if self.item_choices(give).is_empty() {
+                            self.message = Some(if give { "You have no items to give." } else { "You have no items to use." });
+                        } else {
+                            self.item_selection = 0;
+                            self.mode = PartyMode::Items { give };
+                        }
+                    },
+                    "Take" if pokemon[slot].held_item.is_none() => {
+                        self.message = Some("It is not holding anything.");
+                    },
+                    "Take" => {
+                        self.close_submenu();
+                        return PartyEvent::TakeItem(slot);
+                    },
+                    _ => self.close_submenu(),
+                },
+                _ => {},
+            },
+            PartyMode::Items { give } => match key {
+                KeyCode::KeyW if self.item_selection > 0 => self.item_selection -= 1,
+                KeyCode::KeyS if self.item_selection < self.item_choices(give).len() - 1 => self.item_selection += 1,
+                KeyCode::KeyX => self.mode = PartyMode::ItemActions,
+                KeyCode::KeyZ => {
+                    let item = self.item_choices(give)[self.item_selection].0.clone();
+                    self.close_submenu();
+                    return match give {
+                        true => PartyEvent::GiveItem { slot, item },
+                        false => PartyEvent::UseItem { slot, item },
+                    };
+                },
+                _ => {},
+            },
+            PartyMode::Summary => match key {
+                KeyCode::KeyZ => {
+                    self.summary = None;
+                    self.close_submenu();
+                    return PartyEvent::Rename(slot);
+                },
+                KeyCode::KeyX => {
+                    self.summary = None;
+                    self.close_submenu();
+                },
+                _ => {},
+            },
+            PartyMode::Choosing | PartyMode::Switching(_) => {},
+        }
+
+        self.refresh_submenu(renderer);
+        PartyEvent::None
+    }
+
+    // anything but hidden machines can be held, only items with an effect can be used
+    fn item_choices(&self, give: bool) -> Vec<&(String, u32)> {
+        self.items.iter()
+            .filter(|(name, _)| {
+                let item = Item::new(name);
+                if give { item.consumed() } else { item.effect.is_some() }
+            })
+            .collect()
+    }
+
+    fn close_submenu(&mut self) {
+        self.mode = PartyMode::Choosing;
+        self.message = None;
+        self.submenu_text.clear();
+    }
+
+    fn refresh_submenu(&mut self, renderer: &mut Renderer) {
+        let mut text = Vec::new();
+
+        match self.mode {
+            PartyMode::Submenu | PartyMode::ItemActions => {
+                if let Some(message) = self.message.take() {
+                    text.push(Font::new(14.0, 138.0, message, true, "white_font", renderer));
+                }
+                let options = match self.mode {
+                    PartyMode::Submenu => self.submenu_options.clone(),
+                    _ => ITEM_OPTIONS.to_vec(),
+                };
+                for (i, option) in options.iter().enumerate() {
+                    let (column, row) = (i % OPTIONS_PER_ROW, i / OPTIONS_PER_ROW);
+                    text.push(Font::new(14.0 + column as f32 * 56.0, 123.0 + row as f32 * 15.0, option, true, "white_font", renderer));
+                }
+                let (column, row) = (self.submenu_selection % OPTIONS_PER_ROW, self.submenu_selection / OPTIONS_PER_ROW);
+                self.selector.update_position(3.0 + column as f32 * 56.0, 119.0 + row as f32 * 15.0);
+            },
+            PartyMode::Items { give } => {
+                let first_row = self.item_selection - self.item_selection % 2;
+                let rows: Vec<(String, u32)> = self.item_choices(give).into_iter().skip(first_row).take(2).cloned().collect();
+                for (row, (name, count)) in rows.iter().enumerate() {
+                    let y = 123.0 + row as f32 * 15.0;
+                    text.push(Font::new(14.0, y, name, true, "white_font", renderer));
+                    text.push(Font::new(226.0, y, &format!("x {}", count), false, "white_font", renderer));
+                }
+                self.selector.update_position(3.0, 119.0 + (self.item_selection % 2) as f32 * 15.0);
+            },
+            _ => {},
+        }
+
+        self.submenu_text = text;
     }
 
     fn handle_key_press(&mut self, time_held: Duration, direction: i32) {
@@ -131,6 +375,12 @@ impl Party {
     pub fn draw(&self, renderer: &mut Renderer) {
         let mut instances = Vec::new();
 
+        if let Some(summary) = &self.summary {
+            summary.draw(&mut instances);
+            let _ = renderer.render(&instances, false);
+            return;
+        }
+
         instances.extend_from_slice(&self.background.texture);
 
         for slot in &self.slots {
@@ -139,6 +389,14 @@ impl Party {
 
         self.cancel.draw(&mut instances);
 
+        if let PartyMode::Submenu | PartyMode::ItemActions | PartyMode::Items { .. } = self.mode {
+            instances.extend_from_slice(&self.menu_box.texture);
+            instances.extend_from_slice(&self.selector.texture);
+            for line in &self.submenu_text {
+                instances.extend_from_slice(&line.instanced());
+            }
+        }
+
         let _ = renderer.render(&instances, false);
     }
 }
@@ -43,7 +43,7 @@ impl Slot {
         let (tx ,ty) = ((pokemon.id - 1) % 16, (pokemon.id - 1) / 16);
         let pokemon_sprite = renderer.create_sprite(pos_x, pos_y, tx, ty, 1, 1, "pokemon_party", 1.0, 1.0).expect("");
 
-        let name = Font::new(31.0, 37.0, &pokemon.name.to_uppercase(), true, "white_font", renderer);
+        let name = Font::new(31.0, 37.0, &pokemon.display_name().to_uppercase(), true, "white_font", renderer);
         let level = Font::new(47.0, 46.0, &pokemon.level.to_string(), true, "white_font", renderer);
         let max_hp = Font::new(75.0, 62.0, &pokemon.stats.hp.to_string(), false, "white_font", renderer);
         let current_hp = Font::new(55.0, 62.0, &pokemon.current_hp.to_string(), false, "white_font", renderer);
@@ -59,7 +59,7 @@ impl Slot {
         let fainted = pokemon.current_hp == 0;
 
 
-        println!("{} {}", pokemon.name, fainted);
+        println!("{} {}", pokemon.display_name(), fainted);
 
         Self {
             position: 0,
@@ -90,7 +90,7 @@ impl Slot {
         let (tx ,ty) = ((pokemon.id - 1) % 16, (pokemon.id - 1) / 16);
         let pokemon_sprite = renderer.create_sprite(83.0, pos_y - 2.0, tx, ty, 1, 1, "pokemon_party", 1.0, 1.0).expect("");
 
-        let name = Font::new(116.0, pos_y + 7.0, &pokemon.name.to_uppercase(), true, "white_font", renderer);
+        let name = Font::new(116.0, pos_y + 7.0, &pokemon.display_name().to_uppercase(), true, "white_font", renderer);
         let level = Font::new(135.0, pos_y + 17.0, &pokemon.level.to_string(), true, "white_font", renderer);
         let max_hp = Font::new(227.0, pos_y + 17.0, &pokemon.stats.hp.to_string(), false, "white_font", renderer);
         let current_hp = Font::new(207.0, pos_y + 17.0, &pokemon.current_hp.to_string(), false, "white_font", renderer);
@@ -105,7 +105,7 @@ impl Slot {
 
         let fainted = pokemon.current_hp == 0;
 
-        println!("{} {}", pokemon.name, fainted);
+        println!("{} {}", pokemon.display_name(), fainted);
 
         Self {
             position,
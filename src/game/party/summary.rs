@@ -0,0 +1,65 @@
+use crate::renderer::Renderer;
+use crate::renderer::sprite::Sprite;
+use crate::renderer::instance::Instance;
+use crate::game::font::Font;
+use crate::game::pokemon::{Pokemon, Status};
+
+pub struct Summary {
+    top_box: Sprite,
+    bottom_box: Sprite,
+    text: Vec<Font>,
+}
+
+impl Summary {
+    pub fn new(pokemon: &Pokemon, renderer: &mut Renderer) -> Self {
+        let top_box = renderer.create_sprite(0.0, 0.0, 0, 7, 15, 3, "battle", 1.0, 1.0).expect("");
+        let bottom_box = renderer.create_sprite(0.0, 7.0 * 16.0, 0, 7, 15, 3, "battle", 1.0, 1.0).expect("");
+
+        let status = match pokemon.status {
+            None => "OK",
+            Some(Status::Paralysis) => "PAR",
+            Some(Status::Poison) => "PSN",
+            Some(Status::Sleep) => "SLP",
+        };
+
+        let name = match &pokemon.nickname {
+            Some(nickname) => format!("{} / {}", nickname, pokemon.species).to_uppercase(),
+            None => pokemon.species.to_uppercase(),
+        };
+
+        let stats = &pokemon.stats;
+        let mut text = vec![
+            Font::new(14.0, 11.0, &name, true, "white_font", renderer),
+            Font::new(226.0, 11.0, &format!("Lv {}", pokemon.level), false, "white_font", renderer),
+            Font::new(14.0, 26.0, &format!("Status {}", status), true, "white_font", renderer),
+            Font::new(226.0, 26.0, "Z to rename", false, "white_font", renderer),
+            Font::new(14.0, 52.0, &format!("HP {}/{}", pokemon.current_hp, stats.hp), true, "white_font", renderer),
+            Font::new(14.0, 66.0, &format!("Atk {}  Def {}", stats.attack, stats.defense), true, "white_font", renderer),
+            Font::new(14.0, 80.0, &format!("SpA {}  SpD {}", stats.special_attack, stats.special_defense), true, "white_font", renderer),
+            Font::new(14.0, 94.0, &format!("Spe {}", stats.speed), true, "white_font", renderer),
+            Font::new(14.0, 123.0, &format!("Exp {}", pokemon.experience), true, "white_font", renderer),
+            Font::new(226.0, 123.0, pokemon.held_item.as_deref().unwrap_or("No item"), false, "white_font", renderer),
+            Font::new(14.0, 138.0, &format!("To next level {}", pokemon.experience_threshold.saturating_sub(pokemon.experience)), true, "white_font", renderer),
+        ];
+
+        for (i, a_move) in pokemon.moves.iter().take(4).enumerate() {
+            let y = 52.0 + i as f32 * 14.0;
+            text.push(Font::new(126.0, y, &a_move.name, true, "white_font", renderer));
+            text.push(Font::new(226.0, y, &format!("PP {}/{}", a_move.current_pp, a_move.pp), false, "white_font", renderer));
+        }
+
+        Self {
+            top_box,
+            bottom_box,
+            text,
+        }
+    }
+
+    pub fn draw(&self, instances: &mut Vec<Instance>) {
+        instances.extend_from_slice(&self.top_box.texture);
+        instances.extend_from_slice(&self.bottom_box.texture);
+        for line in &self.text {
+            instances.extend_from_slice(&line.instanced());
+        }
+    }
+}
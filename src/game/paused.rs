@@ -1,5 +1,6 @@
 use crate::game::{Game, GameState};
 use crate::renderer::Renderer;
+use crate::game::pokedex::PokedexScreen;
 
 impl Game {
     pub fn paused(&mut self, renderer: &mut Renderer) {
@@ -8,5 +9,9 @@ impl Game {
         if self.state == GameState::Party {
             self.enter_party(renderer);
         }
+
+        if self.state == GameState::Pokedex {
+            self.pokedex_screen = Some(PokedexScreen::new(&self.player.pokedex, renderer));
+        }
     }
 }
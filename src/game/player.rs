@@ -1,10 +1,11 @@
 use crate::renderer::instance::Instance;
-use crate::game::map_loader::Rectangle;
+use crate::game::map_loader::{Map, Terrain};
 use cgmath::{Vector3, Matrix4};
 use std::time::{Duration, Instant};
 use winit::keyboard::KeyCode;
 use crate::game::entity::Entity;
-use crate::game::npc::NPC;
+use crate::game::items::Inventory;
+use crate::game::pokedex::Pokedex;
 use crate::game::animation_player::{AnimationPlayer, Animation, AnimationSheet};
 use crate::renderer::Renderer;
 use std::collections::HashMap;
@@ -17,11 +18,18 @@ const MOVEMENT_DURATION_RUNNING: Duration = Duration::from_millis(125);
 const MOVEMENT_THRESHOLD: Duration = Duration::from_millis(100);
 const ANIMATION_DURATION_WALKING: Duration = Duration::from_millis(125);
 const ANIMATION_DURATION_RUNNING: Duration = Duration::from_millis(67);
+const ANIMATION_DURATION_SURFING: Duration = Duration::from_millis(250);
+const MOVEMENT_DURATION_HOPPING: Duration = Duration::from_millis(400);
+const HOP_HEIGHT: f32 = 0.5;
+const SPIN_TURN_DURATION: u128 = 63;
+// shadow drawn under the player while hopping a ledge
+const SHADOW_TILE: (u32, u32) = (0, 16);
 
 pub struct Player {
     pub position: Vector3<f32>,
     pub target_position: Vector3<f32>,
     pub movement_timer: Duration, // Timer to track movement duration
+    movement_duration: Duration, // Length of the current step, used for the hop arc
     pub animation_player: AnimationPlayer,
     pub input_provided: bool, // Flag to track if input is being provided
     pub time_of_input: Instant, // Time when the input was provided
@@ -29,6 +37,16 @@ pub struct Player {
     pub facing_direction: Vector3<f32>, // Direction of the player
     pub spot_arrival: bool,
     running: bool,
+    pub surfing: bool,
+    hopping: bool,
+    // direction ice, spin or conveyor tiles are moving the player in
+    sliding: Option<Vector3<f32>>,
+    spinning: bool,
+    shadow: Instance,
+    pub money: u32,
+    pub inventory: Inventory,
+    pub badges: Vec<String>,
+    pub pokedex: Pokedex,
 }
 
 impl Entity for Player {
@@ -42,15 +60,22 @@ impl Entity for Player {
 }
 
 impl Player {
-    pub fn new(renderer: &Renderer) -> Self {
+    pub fn new(renderer: &mut Renderer) -> Self {
         let position = Vector3::new(16.0, -12.0, 0.0);
 
+        let atlas = renderer.get_atlas("player").unwrap().clone();
+        let shadow = Instance {
+            model: Matrix4::from_translation(position).into(),
+            tex_index: SHADOW_TILE.0 + SHADOW_TILE.1 * atlas.cols,
+            atlas_index: atlas.index,
+        };
+
         let sheet = AnimationSheet {
             frame_width: 1,
             frame_height: 2,
             frame_order: vec![1, 2, 1, 0],
             frame_duration: ANIMATION_DURATION_WALKING,
-            atlas: renderer.get_atlas("player").unwrap().clone(),
+            atlas,
             looped: true,
         };
 
@@ -66,6 +91,16 @@ impl Player {
         animations.insert("left".to_string(), left_animation);
         animations.insert("right".to_string(), right_animation);
 
+        let surf_sheet = AnimationSheet {
+            frame_order: vec![0, 1, 2, 1],
+            ..sheet
+        };
+
+        animations.insert("surf_down".to_string(), Animation::new(position, &surf_sheet, 0, 8, 3, 2));
+        animations.insert("surf_up".to_string(), Animation::new(position, &surf_sheet, 0, 10, 3, 2));
+        animations.insert("surf_left".to_string(), Animation::new(position, &surf_sheet, 0, 12, 3, 2));
+        animations.insert("surf_right".to_string(), Animation::new(position, &surf_sheet, 0, 14, 3, 2));
+
         let animation_player = AnimationPlayer {
             playing: false,
             animations,
@@ -76,6 +111,7 @@ impl Player {
             position,
             target_position: position,
             movement_timer: Duration::new(0, 0),
+            movement_duration: Duration::new(0, 0),
             animation_player,
             input_provided: false, // Initialize the input_provided flag
             time_of_input: Instant::now(), // Initialize the time_of_input variable
@@ -83,13 +119,25 @@ impl Player {
             facing_direction: Vector3::new(0.0, -1.0, 0.0), // Initialize the direction variable
             spot_arrival: false,
             running: false,
+            surfing: false,
+            hopping: false,
+            sliding: None,
+            spinning: false,
+            shadow,
+            money: 3000,
+            inventory: Inventory::new(),
+            badges: Vec::new(),
+            pokedex: Pokedex::new(),
         }
     }
 
-    pub fn input(&mut self, key: &Option<KeyCode>, input_manager: &mut InputManager, collisions: &Vec<Rectangle>, npcs: &Vec<NPC>) {
+    pub fn input(&mut self, key: &Option<KeyCode>, input_manager: &mut InputManager, map: &Map) {
 
         // Check if the player is running
-        if input_manager.pressed_keys.contains(&KeyCode::KeyX) {
+        if self.surfing {
+            self.running = false;
+            self.animation_player.set_duration(ANIMATION_DURATION_SURFING);
+        } else if input_manager.pressed_keys.contains(&KeyCode::KeyX) {
             self.running = true;
             self.animation_player.set_duration(ANIMATION_DURATION_RUNNING);
         } else {
@@ -142,15 +190,7 @@ impl Player {
         let time_held = Instant::now().duration_since(self.time_of_input);
 
         if self.target_position == self.position && self.input_provided && self.facing_direction != direction {
-            let next_animation = match direction {
-                Vector3 { x: 0.0, y: 1.0, z: 0.0 } => "up",
-                Vector3 { x: -1.0, y: 0.0, z: 0.0 } => "left",
-                Vector3 { x: 0.0, y: -1.0, z: 0.0 } => "down",
-                Vector3 { x: 1.0, y: 0.0, z: 0.0 } => "right",
-                _ => self.animation_player.current_animation.as_str(),
-            };
-
-            self.animation_player.current_animation = next_animation.to_string();
+            self.animation_player.current_animation = self.animation_name(direction);
             self.facing_direction = direction;
 
             if !self.animation_player.playing {
@@ -160,7 +200,7 @@ impl Player {
 
 
         if self.input_provided && time_held > MOVEMENT_THRESHOLD {
-            self.set_direction(direction, collisions, npcs);
+            self.set_direction(direction, map);
         }
     }
 
@@ -168,13 +208,31 @@ impl Player {
 
         self.spot_arrival = false;
 
-        self.animation_player.update(self.position, dt);
+        if self.spinning {
+            let turn = (self.movement_timer.as_millis() / SPIN_TURN_DURATION) as usize % 4;
+            let facing = [
+                Vector3::new(0.0, -1.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(-1.0, 0.0, 0.0),
+            ][turn];
+            self.animation_player.current_animation = self.animation_name(facing);
+        }
+
+        let drawn_position = self.position + Vector3::new(0.0, self.hop_height(), 0.0);
+        if self.animation_player.playing {
+            self.animation_player.update(drawn_position, dt);
+        } else {
+            // sliding along ice or a conveyor without walking
+            self.animation_player.set_position(drawn_position);
+        }
 
         if self.movement_timer > Duration::new(0, 0) {
             if dt >= self.movement_timer {
                 self.position = self.target_position;
                 self.movement_timer = Duration::new(0, 0);
                 self.spot_arrival = true;
+                self.hopping = false;
             } else {
                 // Move the player towards the target position based on the timer
                 let t = dt.as_secs_f32() / self.movement_timer.as_secs_f32();
@@ -187,22 +245,99 @@ impl Player {
         if !self.input_provided && self.target_position == self.position && self.animation_player.playing{
             self.animation_player.stop();
         }
+
+        self.shadow.model = Matrix4::from_translation(self.position).into();
+    }
+
+    // how far above the ground the player is drawn partway through a ledge hop
+    fn hop_height(&self) -> f32 {
+        if !self.hopping || self.movement_duration.is_zero() {
+            return 0.0;
+        }
+
+        let progress = 1.0 - self.movement_timer.as_secs_f32() / self.movement_duration.as_secs_f32();
+        (progress * std::f32::consts::PI).sin() * HOP_HEIGHT
+    }
+
+    pub fn shadow(&self) -> Option<Instance> {
+        self.hopping.then_some(self.shadow)
+    }
+
+    /// Called on arriving at a tile, keeps the player moving across ice, spin and conveyor tiles
+    pub fn resolve_terrain(&mut self, map: &Map) {
+        let direction = match map.terrain_at(self.position) {
+            Some(Terrain::Ice) => self.sliding.unwrap_or(self.facing_direction),
+            Some(Terrain::Spin(direction)) => {
+                self.spinning = true;
+                direction
+            },
+            Some(Terrain::Conveyor(direction)) => direction,
+            _ if self.spinning => self.sliding.unwrap_or(self.facing_direction),
+            _ => {
+                self.sliding = None;
+                return;
+            }
+        };
+
+        let target = self.position + direction * GRID_SIZE;
+        if !map.is_blocked(target, self.surfing) {
+            self.sliding = Some(direction);
+            self.start_move(target, MOVEMENT_DURATION_WALKING);
+            if !self.spinning {
+                self.animation_player.stop();
+            }
+        } else {
+            if self.spinning {
+                self.spinning = false;
+                self.orient(direction);
+            }
+            self.sliding = None;
+        }
+    }
+
+    // drops any step or slide in progress, e.g. when the map changes underneath the player
+    pub fn halt(&mut self) {
+        self.target_position = self.position;
+        self.movement_timer = Duration::new(0, 0);
+        self.hopping = false;
+        self.sliding = None;
+        self.spinning = false;
+    }
+
+    fn start_move(&mut self, target: Vector3<f32>, duration: Duration) {
+        self.target_position = target;
+        self.movement_timer = duration;
+        self.movement_duration = duration;
     }
 
     pub fn orient(&mut self, direction: Vector3<f32>) {
         self.facing_direction = direction;
         self.last_direction = direction;
-        self.animation_player.current_animation = match direction {
+        self.animation_player.current_animation = self.animation_name(direction);
+        self.animation_player.start();
+    }
+
+    pub fn set_surfing(&mut self, surfing: bool) {
+        self.surfing = surfing;
+        self.animation_player.current_animation = self.animation_name(self.facing_direction);
+    }
+
+    fn animation_name(&self, direction: Vector3<f32>) -> String {
+        let name = match direction {
             Vector3 { x: 0.0, y: 1.0, z: 0.0 } => "up",
             Vector3 { x: -1.0, y: 0.0, z: 0.0 } => "left",
-            Vector3 { x: 0.0, y: -1.0, z: 0.0 } => "down",
             Vector3 { x: 1.0, y: 0.0, z: 0.0 } => "right",
             _ => "down",
-        }.to_string();
-        self.animation_player.start();
+        };
+
+        if self.surfing {
+            format!("surf_{}", name)
+        } else {
+            name.to_string()
+        }
     }
 
-    pub fn set_direction(&mut self, new_direction: Vector3<f32>, collisions: &Vec<Rectangle>, npcs: &Vec<NPC>) {
+    pub fn set_direction(&mut self, new_direction: Vector3<f32>, map: &Map) {
         if self.movement_timer > Duration::new(0, 0) {
             // Prevent direction changes while the player is moving
             return;
@@ -218,59 +353,21 @@ impl Player {
             0.0,
         );
 
-        // Calculate the bounding box of the player at the aligned target position
-        let player_left = aligned_target_position.x - 0.5;
-        let player_right = aligned_target_position.x + 0.5;
-        let player_top = aligned_target_position.y + 0.5;
-        let player_bottom = aligned_target_position.y - 0.5;
-
-        // Check for collisions at the aligned target position
-        let mut collision_detected = false;
-        for rect in collisions {
-            let rect_left = rect.x - rect.width / 2.0;
-            let rect_right = rect.x + rect.width / 2.0;
-            let rect_top = rect.y + rect.height / 2.0;
-            let rect_bottom = rect.y - rect.height / 2.0;
-
-            if player_left < rect_right && player_right > rect_left &&
-                player_top > rect_bottom && player_bottom < rect_top {
-                // Collision detected, do not update the target position
-                collision_detected = true;
-                break;
-            }
-        }
-
-        for npc in npcs {
-            let npc_left = npc.position.x - 0.5;
-            let npc_right = npc.position.x + 0.5;
-            let npc_top = npc.position.y + 0.5;
-            let npc_bottom = npc.position.y - 0.5;
-
-            if player_left < npc_right && player_right > npc_left &&
-                player_top > npc_bottom && player_bottom < npc_top {
-                // Collision detected, do not update the target position
-                collision_detected = true;
-                break;
-            }
-
-            if aligned_target_position == npc.target_position {
-                collision_detected = true;
-                break;
+        if let Some(Terrain::Ledge(ledge_direction)) = map.terrain_at(aligned_target_position) {
+            // ledges are only crossed going down them, landing on the far side
+            let landing = aligned_target_position + ledge_direction * GRID_SIZE;
+            if ledge_direction == new_direction && !map.is_blocked(landing, self.surfing) {
+                self.hopping = true;
+                self.start_move(landing, MOVEMENT_DURATION_HOPPING);
             }
-        }
-
-        if !collision_detected {
-            self.target_position = aligned_target_position;
+        } else if !map.is_blocked(aligned_target_position, self.surfing) {
             if self.running {
-                self.movement_timer = MOVEMENT_DURATION_RUNNING;
+                self.start_move(aligned_target_position, MOVEMENT_DURATION_RUNNING);
             } else {
-                self.movement_timer = MOVEMENT_DURATION_WALKING;
+                self.start_move(aligned_target_position, MOVEMENT_DURATION_WALKING);
             }
         }
 
-
         self.animation_player.start();
     }
-
-
 }
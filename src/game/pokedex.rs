@@ -0,0 +1,243 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use winit::keyboard::KeyCode;
+use crate::game::dialogue::wrap;
+use crate::game::font::Font;
+use crate::game::input_manager::InputManager;
+use crate::renderer::Renderer;
+use crate::renderer::sprite::Sprite;
+use crate::assets;
+
+const SPECIES_FILE: &str = "pokedex.txt";
+const ROWS_PER_PAGE: usize = 4;
+const HOLD_DURATION: Duration = Duration::from_millis(500);
+const HOLD_TIME_PER_ROW: Duration = Duration::from_millis(80);
+
+// species seen and caught by the player, by national id
+pub struct Pokedex {
+    pub seen: BTreeSet<u32>,
+    pub caught: BTreeSet<u32>,
+}
+
+impl Pokedex {
+    pub fn new() -> Self {
+        Self {
+            seen: BTreeSet::new(),
+            caught: BTreeSet::new(),
+        }
+    }
+
+    pub fn see(&mut self, id: u32) {
+        self.seen.insert(id);
+    }
+
+    pub fn catch(&mut self, id: u32) {
+        self.seen.insert(id);
+        self.caught.insert(id);
+    }
+}
+
+pub struct SpeciesEntry {
+    pub id: u32,
+    pub name: String,
+    pub types: String,
+    pub height: f32,
+    pub weight: f32,
+    pub flavor: String,
+}
+
+// one species per line: id|name|types|height m|weight kg|flavor text
+fn load_species(path: &Path) -> Result<Vec<SpeciesEntry>> {
+    let contents = fs::read_to_string(path).context("Failed to read species data")?;
+
+    contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.splitn(6, '|').collect();
+            if fields.len() != 6 {
+                return Err(anyhow!("Species line needs 6 fields: {}", line));
+            }
+
+            Ok(SpeciesEntry {
+                id: fields[0].parse().context("Invalid species id")?,
+                name: fields[1].to_string(),
+                types: fields[2].replace('/', " / "),
+                height: fields[3].parse().context("Invalid height")?,
+                weight: fields[4].parse().context("Invalid weight")?,
+                flavor: fields[5].to_string(),
+            })
+        })
+        .collect()
+}
+
+pub struct PokedexScreen {
+    species: Vec<SpeciesEntry>,
+    selected: usize,
+    detail: bool,
+    top_box: Sprite,
+    bottom_box: Sprite,
+    selector: Sprite,
+    text: Vec<Font>,
+    sprite: Option<Sprite>,
+    last_key: Option<KeyCode>,
+    time_of_input: Instant,
+    time_of_hold: Instant,
+}
+
+impl PokedexScreen {
+    pub fn new(pokedex: &Pokedex, renderer: &mut Renderer) -> Self {
+        let species = load_species(&assets::get().path(SPECIES_FILE)).unwrap_or_else(|e| {
+            eprintln!("Failed to load pokedex: {:?}", e);
+            Vec::new()
+        });
+
+        let top_box = renderer.create_sprite(0.0, 0.0, 0, 7, 15, 3, "battle", 1.0, 1.0).expect("");
+        let bottom_box = renderer.create_sprite(0.0, 7.0 * 16.0, 0, 7, 15, 3, "battle", 1.0, 1.0).expect("");
+        let selector = renderer.create_sprite(3.0, 49.0, 13, 10, 1, 1, "battle", 1.0, 1.0).expect("");
+
+        let mut screen = Self {
+            species,
+            selected: 0,
+            detail: false,
+            top_box,
+            bottom_box,
+            selector,
+            text: Vec::new(),
+            sprite: None,
+            last_key: None,
+            time_of_input: Instant::now(),
+            time_of_hold: Instant::now(),
+        };
+
+        screen.refresh(pokedex, renderer);
+        screen
+    }
+
+    /// Returns true once the pokedex is closed
+    pub fn update(&mut self, pokedex: &Pokedex, input_manager: &mut InputManager, renderer: &mut Renderer) -> bool {
+        let held_key = input_manager.get_last_key();
+        let single_press_key = input_manager.get_key_on_press();
+
+        if self.detail {
+            if let Some(KeyCode::KeyX | KeyCode::KeyZ) = single_press_key {
+                self.detail = false;
+                self.refresh(pokedex, renderer);
+            }
+            return false;
+        }
+
+        match single_press_key {
+            Some(KeyCode::KeyX) => return true,
+            Some(KeyCode::KeyZ) => {
+                if self.species.get(self.selected).map_or(false, |entry| pokedex.seen.contains(&entry.id)) {
+                    self.detail = true;
+                    self.refresh(pokedex, renderer);
+                }
+                return false;
+            },
+            _ => {},
+        }
+
+        // holding a direction keeps scrolling after a short delay
+        if held_key != self.last_key {
+            self.last_key = held_key;
+            self.time_of_input = Instant::now();
+        }
+
+        let step = match held_key {
+            Some(KeyCode::KeyW) => -1,
+            Some(KeyCode::KeyS) => 1,
+            Some(KeyCode::KeyA) => -(ROWS_PER_PAGE as i32),
+            Some(KeyCode::KeyD) => ROWS_PER_PAGE as i32,
+            _ => 0,
+        };
+
+        let first_press = single_press_key == held_key && step != 0;
+        let repeating = self.time_of_input.elapsed() > HOLD_DURATION && self.time_of_hold.elapsed() > HOLD_TIME_PER_ROW;
+
+        if step != 0 && (first_press || repeating) {
+            let last = self.species.len().saturating_sub(1) as i32;
+            self.selected = (self.selected as i32 + step).clamp(0, last) as usize;
+            self.time_of_hold = Instant::now();
+            self.refresh(pokedex, renderer);
+        }
+
+        false
+    }
+
+    fn refresh(&mut self, pokedex: &Pokedex, renderer: &mut Renderer) {
+        let mut text = Vec::new();
+        self.sprite = None;
+
+        if self.detail {
+            let entry = &self.species[self.selected];
+
+            text.push(Font::new(14.0, 11.0, &format!("{:03} {}", entry.id, entry.name), true, "white_font", renderer));
+            text.push(Font::new(14.0, 26.0, &entry.types, true, "white_font", renderer));
+
+            let sprite_coords = ((entry.id - 1) % 16 * 2, (entry.id - 1) / 16 * 2);
+            self.sprite = renderer.create_sprite(16.0, 48.0, sprite_coords.0, sprite_coords.1, 2, 2, "pokemon_front", 1.0, 1.0).ok();
+
+            if pokedex.caught.contains(&entry.id) {
+                text.push(Font::new(112.0, 60.0, &format!("Ht {:.1} m", entry.height), true, "white_font", renderer));
+                text.push(Font::new(112.0, 80.0, &format!("Wt {:.1} kg", entry.weight), true, "white_font", renderer));
+
+                for (i, line) in wrap(&entry.flavor).iter().take(2).enumerate() {
+                    text.push(Font::new(14.0, 123.0 + i as f32 * 15.0, line, true, "white_font", renderer));
+                }
+            } else {
+                text.push(Font::new(14.0, 123.0, "Catch one to learn more.", true, "white_font", renderer));
+            }
+        } else {
+            text.push(Font::new(14.0, 11.0, "Pokedex", true, "white_font", renderer));
+            text.push(Font::new(14.0, 26.0, &format!("Seen {}", pokedex.seen.len()), true, "white_font", renderer));
+            text.push(Font::new(226.0, 26.0, &format!("Owned {}", pokedex.caught.len()), false, "white_font", renderer));
+
+            let first_row = self.selected - self.selected % ROWS_PER_PAGE;
+            for row in 0..ROWS_PER_PAGE {
+                let Some(entry) = self.species.get(first_row + row) else {
+                    break;
+                };
+
+                let y = 53.0 + row as f32 * 15.0;
+                let name = if pokedex.seen.contains(&entry.id) { entry.name.as_str() } else { "----------" };
+                text.push(Font::new(14.0, y, &format!("{:03} {}", entry.id, name), true, "white_font", renderer));
+
+                if pokedex.caught.contains(&entry.id) {
+                    text.push(Font::new(226.0, y, "owned", false, "white_font", renderer));
+                }
+            }
+
+            let row = self.selected % ROWS_PER_PAGE;
+            self.selector.update_position(3.0, 49.0 + row as f32 * 15.0);
+
+            text.push(Font::new(14.0, 123.0, "Z to view", true, "white_font", renderer));
+            text.push(Font::new(14.0, 138.0, "X to close", true, "white_font", renderer));
+        }
+
+        self.text = text;
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer) {
+        let mut instances = Vec::new();
+
+        instances.extend_from_slice(&self.top_box.texture);
+        instances.extend_from_slice(&self.bottom_box.texture);
+
+        if let Some(sprite) = &self.sprite {
+            instances.extend_from_slice(&sprite.texture);
+        } else {
+            instances.extend_from_slice(&self.selector.texture);
+        }
+
+        for line in &self.text {
+            instances.extend_from_slice(&line.instanced());
+        }
+
+        let _ = renderer.render(&instances, false);
+    }
+}
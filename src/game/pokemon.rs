@@ -4,7 +4,9 @@ use crate::renderer::Renderer;
 
 #[derive(Clone)]
 pub struct Pokemon {
-    pub name: String,
+    pub species: String,
+    pub nickname: Option<String>,
+    pub held_item: Option<String>,
     pub level: u32,
     pub current_hp: u32,
     pub stats: Stats,
@@ -17,11 +19,21 @@ pub struct Pokemon {
     pub experience: u32,
     pub experience_threshold: u32,
     pub experience_yield: u32,
+    pub status: Option<Status>,
+    // turns left before waking up while asleep
+    pub sleep_turns: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Status {
+    Paralysis,
+    Poison,
+    Sleep,
 }
 
 impl Pokemon {
-     pub fn new(name: String, level: u32, renderer: &mut Renderer) -> Self {
-        let (id, base_stats, catch_rate, experience_yield, move_names) = match name.as_str() {
+     pub fn new(species: String, level: u32, renderer: &mut Renderer) -> Self {
+        let (id, base_stats, catch_rate, experience_yield, move_names) = match species.as_str() {
             "Bulbasaur" => (
                 1,
                 Stats::new(45, 49, 49, 45, 65, 65),
@@ -1111,7 +1123,9 @@ impl Pokemon {
         let experience_threshold = level.pow(3);
 
         Self {
-            name,
+            species,
+            nickname: None,
+            held_item: None,
             level,
             current_hp: stats.hp,
             stats,
@@ -1123,10 +1137,17 @@ impl Pokemon {
             catch_rate,
             experience: 0,
             experience_threshold,
-            experience_yield
+            experience_yield,
+            status: None,
+            sleep_turns: 0,
         }
     }
 
+    // the nickname if one was given, otherwise the species
+    pub fn display_name(&self) -> &str {
+        self.nickname.as_deref().unwrap_or(&self.species)
+    }
+
     pub fn gain_experience(&mut self, experience: u32) {
         self.experience += experience;
 
@@ -1193,3 +1214,20 @@ impl Stats {
         }
     }
 }
+
+// (common, rare) items wild pokemon of a species can be found holding
+pub fn wild_held_items(species: &str) -> (Option<&'static str>, Option<&'static str>) {
+    match species {
+        "Bulbasaur" | "Ivysaur" | "Venusaur" | "Oddish" | "Bellsprout" => (None, Some("Miracle Seed")),
+        "Charmander" | "Charmeleon" | "Charizard" | "Vulpix" | "Growlithe" => (None, Some("Charcoal")),
+        "Squirtle" | "Wartortle" | "Blastoise" | "Psyduck" | "Poliwag" | "Staryu" => (None, Some("Mystic Water")),
+        "Pikachu" | "Magnemite" | "Voltorb" => (Some("Oran Berry"), Some("Magnet")),
+        "Caterpie" | "Metapod" | "Butterfree" | "Weedle" | "Kakuna" | "Beedrill" => (Some("Oran Berry"), Some("Silver Powder")),
+        "Pidgey" | "Pidgeotto" | "Pidgeot" | "Spearow" | "Fearow" => (None, Some("Sharp Beak")),
+        "Ekans" | "Nidoran♀" | "Nidoran♂" | "Tentacool" => (None, Some("Poison Barb")),
+        "Rattata" | "Raticate" | "Meowth" | "Jigglypuff" => (Some("Oran Berry"), Some("Silk Scarf")),
+        "Chansey" => (Some("Sitrus Berry"), None),
+        "Snorlax" => (Some("Leftovers"), None),
+        _ => (None, None),
+    }
+}
@@ -6,18 +6,28 @@ use tiled::Loader;
 use crate::game::gamestate::GameState;
 use rand::Rng;
 use crate::game::pokemon::Pokemon;
-use crate::game::map_loader::{Door, Map, Grass};
-use super::npc::{NPC, generate_pokemon};
-use crate::game::Interaction;
+use crate::game::map_loader::{Door, Map};
+use super::npc::NPC;
+use crate::game::{Interaction, HealLocation};
 use cgmath::Vector3;
 use crate::game::animation_player::{Animation, AnimationSheet};
+use crate::game::transition::Destination;
+use crate::assets;
 
 impl Game {
     pub fn running(&mut self, renderer: &mut Renderer, dt: Duration) {
-        renderer.update(self.player.position);
+        self.update_camera(renderer, dt);
+
+        if self.script.is_some() {
+            self.run_script(renderer, dt);
+            if self.state != GameState::Running {
+                return;
+            }
+        }
+        let scripted = self.script.is_some();
 
         let last_key = self.input_manager.get_last_key();
-        let single_press_key = self.input_manager.get_key_on_press();
+        let single_press_key = self.input_manager.get_key_on_press().filter(|_| !scripted);
 
         if let Some(key) = single_press_key {
             match key {
@@ -25,39 +35,64 @@ impl Game {
                     self.state = GameState::Paused;
                     return;
                 }
+                KeyCode::KeyC => {
+                    self.open_trainer_card(renderer);
+                    return;
+                }
                 KeyCode::KeyZ => {
+                    if self.interact_field_obstacle(renderer) {
+                        return;
+                    }
+
                     //check if player is if front of interaction
-                    let mut interaction_detected: Option<&str> = None;
+                    let facing = self.player.facing_direction + self.player.position;
+                    let interaction_detected = self.map.interaction_at(facing).cloned();
 
-                    for interaction in &self.map.interactions {
-                        //check if player is facing interaction
-                        if self.player.facing_direction + self.player.position == cgmath::Vector3::new(interaction.x, interaction.y, 0.0) {
-                            interaction_detected = Some(&interaction.name);
-                            break;
+                    if let Some(interaction) = interaction_detected {
+                        if let Some(script) = &interaction.script {
+                            self.start_script(script);
                         }
 
-                    }
-
-                    if let Some(interaction) = interaction_detected {
-                        match interaction {
+                        match interaction.name.as_str() {
                             "Heal" => {
+                                self.heal_location = Some(HealLocation {
+                                    map_name: self.map.name.clone(),
+                                    position: self.player.position,
+                                    direction: self.player.facing_direction,
+                                });
                                 self.heal_pokemon();
                             }
+                            "Shop" => {
+                                //the counter opens the shop of whichever clerk is behind it
+                                let stock = self.npcs.iter().find_map(|npc| match &npc.interaction {
+                                    Interaction::Shop(stock) => Some(stock.clone()),
+                                    _ => None,
+                                });
+
+                                if let Some(stock) = stock {
+                                    self.open_shop(&stock, renderer);
+                                    return;
+                                }
+                            }
                             _ => {}
                         }
                     }
 
                     //if player is in front of battle npc then start battle
-                    let npc = self.npcs.iter().find(|npc| {
-                        if let Interaction::Battle(battled, _) = npc.interaction {
-                            !battled && npc.position == self.player.facing_direction + self.player.position
-                        } else {
-                            false
-                        }
-                    });
+                    let npc = self.map.npc_at(facing)
+                        .and_then(|id| self.npcs.iter().find(|npc| npc.id.1 == id));
+                    let battle = npc.filter(|npc| matches!(npc.interaction, Interaction::Battle(false, _)));
 
-                    if let Some(npc) = npc {
+                    if let Some(npc) = battle {
                         self.queue_battle = (true, npc.id.clone());
+                    } else {
+                        let script = npc
+                            .and_then(|npc| self.map.npcs.iter().find(|map_npc| map_npc.id == npc.id.1))
+                            .and_then(|map_npc| map_npc.script.clone());
+
+                        if let Some(script) = script {
+                            self.start_script(&script);
+                        }
                     }
 
                 }
@@ -75,13 +110,22 @@ impl Game {
             if npc.position == npc.next_point {
                 let id = npc.id.clone();
 
-                self.start_battle(id.clone(), generate_pokemon(id.0.clone(), id.1, renderer), renderer);
+                self.begin_transition(Destination::Battle(id));
 
                 self.queue_battle = (false, ("".to_string(), 0));
             }
         } else {
-            self.player.input(&last_key, &mut self.input_manager, &self.map.collisions, &self.npcs);
+            self.track_npcs();
+            if !scripted {
+                self.player.input(&last_key, &mut self.input_manager, &self.map);
+                self.push_boulder();
+            }
+            self.check_connections(renderer);
             self.player.update(dt);
+            if self.player.spot_arrival {
+                self.check_surfing();
+                self.player.resolve_terrain(&self.map);
+            }
             for npc in &mut self.npcs {
                 npc.update(self.player.target_position, dt);
 
@@ -113,63 +157,69 @@ impl Game {
             self.foreground_animations.remove(index);
         }
 
-        //for each door check if player collides with door
+        //check if the player has reached a door
         //if so load new map and texture
-        let mut door_detected: Option<Door> = None;
-        for door in &self.map.doors {
-            let rect = &door.rectangle;
-            let player_left = self.player.position.x - 0.5;
-            let player_right = self.player.position.x + 0.5;
-            let player_top = self.player.position.y + 0.5;
-            let player_bottom = self.player.position.y - 0.5;
-
-            let rect_left = rect.x - rect.width / 4.0;
-            let rect_right = rect.x + rect.width / 4.0;
-            let rect_top = rect.y + rect.height / 4.0;
-            let rect_bottom = rect.y - rect.height / 4.0;
-
-            if player_left < rect_right && player_right > rect_left &&
-               player_top > rect_bottom && player_bottom < rect_top {
-                door_detected = Some(door.clone());
-                break;
-            }
-        }
+        let door_detected: Option<Door> = self.map.door_at(self.player.position).cloned();
 
         if let Some(door) = door_detected {
-            self.load_map(&door.name, door.location, renderer);
+            match &door.requires {
+                Some(flag) if !self.flags.contains(flag) => {
+                    println!("Door to {} needs {}", door.name, flag);
+                    //step back off the door
+                    self.player.position = self.player.target_position - self.player.facing_direction;
+                    self.player.target_position = self.player.position;
+                    self.show_dialogue(&["The way ahead is blocked."], renderer);
+                    return;
+                },
+                _ => {
+                    self.begin_transition(Destination::Door { map_name: door.name, location: door.location });
+                    return;
+                },
+            }
         }
 
-        let mut grass_detected: Option<Grass> = None;
+        if !scripted && self.player.spot_arrival {
+            let trigger = self.map.trigger_at(self.player.position)
+                .filter(|trigger| trigger.until.as_ref().map_or(true, |flag| !self.flags.contains(flag)))
+                .map(|trigger| trigger.script.clone());
 
-        for grass in &self.map.grasses {
-            if self.player.position.x == grass.x && self.player.position.y == grass.y && self.player.spot_arrival {
-                grass_detected = Some(grass.clone());
-                break;
+            if let Some(script) = trigger {
+                self.start_script(&script);
+                return;
             }
         }
 
-        if let Some(grass) = grass_detected {
+        if self.player.spot_arrival && self.player.surfing && self.rng.gen_range(0..10) == 0 {
+            println!("Wild Pokemon appeared from the water!");
+            let pokemon = match self.rng.gen_range(0..3) {
+                0 => Pokemon::new("Tentacool".to_string(), 10, renderer),
+                1 => Pokemon::new("Psyduck".to_string(), 10, renderer),
+                _ => Pokemon::new("Poliwag".to_string(), 10, renderer),
+            };
+
+            self.begin_transition(Destination::Encounter(pokemon));
+            return;
+        }
+
+        if self.player.spot_arrival && self.map.is_grass(self.player.position) {
             let sheet = AnimationSheet {
                 frame_width: 1,
                 frame_height: 1,
                 frame_order: vec![0, 1, 2],
                 frame_duration: Duration::from_millis(100),
-                atlas: renderer.get_atlas("landing").unwrap().clone(),
+                atlas: renderer.get_atlas(&self.animation_texture).unwrap().clone(),
                 looped: false,
             };
 
-            let position = cgmath::Vector3::new(grass.x, grass.y, 0.0);
-
-            let animation = Animation::new(position, &sheet, 5, 0, 3, 1);
+            let animation = Animation::new(self.player.position, &sheet, 5, 0, 3, 1);
 
             self.foreground_animations.push(animation);
 
-            let mut rng = rand::thread_rng();
-            let random_number = rng.gen_range(0..8);
+            let random_number = self.rng.gen_range(0..8);
 
             if random_number == 0 {
                 println!("Wild Pokemon appeared!");
-                let random_pokemon = rng.gen_range(0..3);
+                let random_pokemon = self.rng.gen_range(0..3);
 
                 let pokemon = match random_pokemon {
                     0 => Pokemon::new("Bulbasaur".to_string(), 5, renderer),
@@ -179,27 +229,89 @@ impl Game {
                 };
 
 
-                self.start_encounter(pokemon, renderer);
+                self.begin_transition(Destination::Encounter(pokemon));
             }
         }
     }
 
+    // npcs are put in the grid on the tile they stand on and the one they are walking onto
+    pub fn track_npcs(&mut self) {
+        self.map.grid.clear_npcs();
+        for npc in &self.npcs {
+            self.map.grid.occupy(npc.position, npc.id.1);
+            self.map.grid.occupy(npc.target_position, npc.id.1);
+        }
+    }
+
     pub fn heal_pokemon(&mut self) {
         for pokemon in &mut self.player_pokemon {
             pokemon.current_hp = pokemon.stats.hp;
+            pokemon.status = None;
+            for a_move in &mut pokemon.moves {
+                a_move.current_pp = a_move.pp;
+            }
         }
 
         println!("Your Pokémon have been healed!");
     }
 
-    pub fn load_map(&mut self, map_name: &str, door_location: u32 , renderer: &mut Renderer) {
+    /// Reads a map along with its tileset texture, which stays held until the map is let go of
+    pub fn read_map(map_name: &str, renderer: &mut Renderer) -> Map {
         let mut loader = Loader::new();
-        let map_path = format!("/home/chris/games/SirSquare/assets/{}.tmx", map_name);
+        let map_path = assets::get().map_path(map_name).unwrap();
         let map_loader = loader.load_tmx_map(map_path).unwrap();
+        let atlas = renderer.hold_texture(map_name).unwrap();
+
+        Map::new(&map_loader, atlas.index, map_name.to_string())
+    }
+
+    pub fn load_map(&mut self, map_name: &str, door_location: u32 , renderer: &mut Renderer) {
+        let mut map = Self::read_map(map_name, renderer);
+        map.upload(renderer);
+        let previous = std::mem::replace(&mut self.map, map);
+        self.load_connections(Some(previous), renderer);
+
+        //search map for player spawn that matches door.location
+        //the spawn name must be player also
+        let player_spawn = self.map.spawns.iter().find(|spawn| spawn.name == "player" && spawn.location == door_location);
+
+        //callers placing the player themselves (e.g. blacking out) may pass a door with no spawn
+        if let Some(spawn) = player_spawn {
+            self.player.orient(spawn.direction);
+            self.player.position = cgmath::Vector3::new(spawn.x, spawn.y, 0.0);
+        }
+        self.player.halt();
+        self.player.set_surfing(self.map.is_water(self.player.position));
+
+        self.populate_map(renderer);
+
+        self.play_map_music();
+    }
+
+    /// Plays a track listed under music in the asset manifest
+    pub fn play_music(&mut self, name: &str) {
+        match assets::get().music_path(name) {
+            Some(path) => self.audio_player.play(&path.to_string_lossy()),
+            None => println!("Music {} is not in the asset manifest", name),
+        }
+    }
 
-        self.map = Map::new(&map_loader, 0, map_name.to_string());
+    // each map names its music in the asset manifest
+    pub fn play_map_music(&mut self) {
+        if let Some(path) = assets::get().map_music(&self.map.name) {
+            self.audio_player.play(&path.to_string_lossy());
+        }
+    }
 
-        let _ = renderer.update_texture(0, map_name, 16, 16);
+    // animations and npcs for the current map, also used when walking across a connection
+    pub fn populate_map(&mut self, renderer: &mut Renderer) {
+        let map_name = self.map.name.clone();
+
+        // the previous map's animations go with it, along with the hold on its texture
+        let atlas = renderer.hold_texture(&map_name).unwrap().clone();
+        let previous = std::mem::replace(&mut self.animation_texture, map_name.clone());
+        renderer.release_texture(&previous);
+        self.foreground_animations = Vec::new();
 
         let mut ground_animations = Vec::new();
 
@@ -210,7 +322,7 @@ impl Game {
                 frame_height: 1,
                 frame_order: vec![0, 1, 2, 3],
                 frame_duration: Duration::from_millis(250),
-                atlas: renderer.get_atlas("landing").unwrap().clone(),
+                atlas: atlas.clone(),
                 looped: true,
             };
 
@@ -221,18 +333,11 @@ impl Game {
         }
 
         self.ground_animations = ground_animations;
-
-        //search map for player spawn that matches door.location
-        //the spawn name must be player also
-        let player_spawn = self.map.spawns.iter().find(|spawn| spawn.name == "player" && spawn.location == door_location);
-
-        self.player.orient(player_spawn.unwrap().direction);
-        self.player.position = cgmath::Vector3::new(player_spawn.unwrap().x, player_spawn.unwrap().y, 0.0);
-        self.player.target_position = self.player.position;
+        self.strength = false;
 
         self.npcs = Vec::new();
 
-        for npc in &self.map.npcs {
+        for npc in self.map.npcs.iter().filter(|npc| npc.present(&self.flags)) {
             let position = cgmath::Vector3::new(npc.x, npc.y, 0.0);
             let path = npc.path_id
                 .and_then(|path_id| self.map.paths.iter().find(|path| path.id == path_id).map(|p| p.points.clone()));
@@ -251,20 +356,14 @@ impl Game {
                     Interaction::Battle(battled, battle_squares)
                 },
                 "Talk" => Interaction::Talk,
+                "Shop" => Interaction::Shop(npc.stock.clone()),
                 _ => Interaction::None,
             };
 
-            let new_npc = NPC::new((map_name.to_string(), npc.id), position, npc.direction, &npc.name, interaction, npc.los, path, renderer);
+            let new_npc = NPC::new((map_name.clone(), npc.id), position, npc.direction, &npc.name, interaction, npc.los, path, renderer);
 
             self.npcs.push(new_npc);
         }
-
-        match map_name {
-            "pokecenter" => self.audio_player.play("/home/chris/games/SirSquare/assets/Pokemon Center.mp3"),
-            "gym" => self.audio_player.play("/home/chris/games/SirSquare/assets/Pokemon Gym.mp3"),
-            _ => self.audio_player.play("/home/chris/games/SirSquare/assets/Pallet Town.mp3"),
-        }
-
     }
 
 }
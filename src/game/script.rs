@@ -0,0 +1,441 @@
+use anyhow::{anyhow, Context, Result};
+use cgmath::Vector3;
+use std::collections::VecDeque;
+use std::fs;
+use std::time::Duration;
+use crate::game::{Game, GameState};
+use crate::game::transition::Destination;
+use crate::game::camera::CameraTarget;
+use crate::renderer::Renderer;
+use crate::assets;
+
+// Scripts are attached to npcs, interactions and triggers through a "script" property in Tiled.
+// Commands are separated by new lines or semicolons:
+//
+//   text Welcome to the gym.
+//   unless <Cascade Badge> text Come back with a badge.
+//   give 2 Potion
+//   set talked_to_guard
+//   walk 12 up 3
+//   walk player left 1
+//   face player up
+//   camera 12
+//   camera 20 14
+//   camera player
+//   wait 500
+//   fade out
+//   battle 12
+//   warp gym 1
+//   sound Pokemon Gym.mp3
+//   heal
+//   cutscene gym_intro
+//
+// camera pans over to an npc, the player or a tile given in Tiled coordinates and waits
+// until it gets there. It pans back to the player once the script ends.
+// if/unless run the rest of the line only when the flag is (or isn't) set,
+// flag names containing spaces are wrapped in <>.
+// sound plays a sound named in the asset manifest, or else a file in the asset folder.
+// cutscene splices in the commands from assets/<name>.cutscene, so longer
+// sequences can live in files next to the maps. Player input is locked while a script runs.
+
+#[derive(Clone, Copy, Debug)]
+pub enum Actor {
+    Player,
+    Npc(u32),
+}
+
+#[derive(Clone, Debug)]
+pub enum Command {
+    Text(String),
+    GiveItem { name: String, amount: u32 },
+    SetFlag(String),
+    ClearFlag(String),
+    If { flag: String, expected: bool, command: Box<Command> },
+    Walk { actor: Actor, direction: Vector3<f32>, steps: u32 },
+    Face { actor: Actor, direction: Vector3<f32> },
+    Camera(CameraTarget),
+    Wait(Duration),
+    Fade { out: bool },
+    Cutscene(String),
+    Battle(u32),
+    Warp { map_name: String, location: u32 },
+    Sound(String),
+    Heal,
+}
+
+// what the script is blocked on before running the next command
+pub enum Wait {
+    Npc(u32),
+    Player { direction: Vector3<f32>, steps: u32 },
+    Timer(Duration),
+    Camera,
+    Fade,
+}
+
+pub struct Script {
+    commands: VecDeque<Command>,
+    pub waiting_on: Option<Wait>,
+}
+
+impl Script {
+    pub fn parse(source: &str) -> Result<Self> {
+        let commands = source
+            .split(|c| c == '\n' || c == ';')
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| Command::parse(line).with_context(|| format!("Bad script command: {}", line)))
+            .collect::<Result<VecDeque<_>>>()?;
+
+        Ok(Self {
+            commands,
+            waiting_on: None,
+        })
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let source = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+        Self::parse(&source)
+    }
+
+    pub fn next_command(&mut self) -> Option<Command> {
+        self.commands.pop_front()
+    }
+
+    // runs another script's commands before the rest of this one
+    fn splice(&mut self, other: Script) {
+        for command in other.commands.into_iter().rev() {
+            self.commands.push_front(command);
+        }
+    }
+}
+
+fn parse_direction(name: &str) -> Result<Vector3<f32>> {
+    match name {
+        "up" => Ok(Vector3::new(0.0, 1.0, 0.0)),
+        "down" => Ok(Vector3::new(0.0, -1.0, 0.0)),
+        "left" => Ok(Vector3::new(-1.0, 0.0, 0.0)),
+        "right" => Ok(Vector3::new(1.0, 0.0, 0.0)),
+        other => Err(anyhow!("unknown direction {}", other)),
+    }
+}
+
+fn parse_actor(name: &str) -> Result<Actor> {
+    match name {
+        "player" => Ok(Actor::Player),
+        id => Ok(Actor::Npc(id.parse().context("actor must be player or an npc id")?)),
+    }
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Self> {
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        let command = match keyword {
+            "text" => Command::Text(rest.to_string()),
+            "give" => {
+                let (amount, name) = rest.split_once(' ').ok_or_else(|| anyhow!("give needs an amount and an item"))?;
+                Command::GiveItem { name: name.trim().to_string(), amount: amount.parse()? }
+            },
+            "set" => Command::SetFlag(rest.to_string()),
+            "clear" => Command::ClearFlag(rest.to_string()),
+            "if" | "unless" => {
+                let (flag, command) = match rest.strip_prefix('<') {
+                    Some(quoted) => quoted.split_once('>').ok_or_else(|| anyhow!("unclosed flag name"))?,
+                    None => rest.split_once(' ').ok_or_else(|| anyhow!("{} needs a flag and a command", keyword))?,
+                };
+
+                Command::If {
+                    flag: flag.trim().to_string(),
+                    expected: keyword == "if",
+                    command: Box::new(Command::parse(command.trim())?),
+                }
+            },
+            "walk" => {
+                let parts: Vec<&str> = rest.split_whitespace().collect();
+                if parts.len() != 3 {
+                    return Err(anyhow!("walk needs an actor, a direction and a number of steps"));
+                }
+
+                Command::Walk { actor: parse_actor(parts[0])?, direction: parse_direction(parts[1])?, steps: parts[2].parse()? }
+            },
+            "face" => {
+                let (actor, direction) = rest.split_once(' ').ok_or_else(|| anyhow!("face needs an actor and a direction"))?;
+                Command::Face { actor: parse_actor(actor)?, direction: parse_direction(direction.trim())? }
+            },
+            "camera" => match rest.split_once(' ') {
+                Some((x, y)) => Command::Camera(CameraTarget::Position(Vector3::new(x.parse()?, -y.trim().parse::<f32>()?, 0.0))),
+                None => match parse_actor(rest)? {
+                    Actor::Player => Command::Camera(CameraTarget::Player),
+                    Actor::Npc(id) => Command::Camera(CameraTarget::Npc(id)),
+                },
+            },
+            "wait" => Command::Wait(Duration::from_millis(rest.parse()?)),
+            "fade" => match rest {
+                "out" => Command::Fade { out: true },
+                "in" => Command::Fade { out: false },
+                _ => return Err(anyhow!("fade is either in or out")),
+            },
+            "cutscene" => Command::Cutscene(rest.to_string()),
+            "battle" => Command::Battle(rest.parse()?),
+            "warp" => {
+                let (map_name, location) = rest.rsplit_once(' ').ok_or_else(|| anyhow!("warp needs a map and a door location"))?;
+                Command::Warp { map_name: map_name.to_string(), location: location.parse()? }
+            },
+            "sound" => Command::Sound(rest.to_string()),
+            "heal" => Command::Heal,
+            _ => return Err(anyhow!("unknown command {}", keyword)),
+        };
+
+        Ok(command)
+    }
+}
+
+impl Game {
+    pub fn start_script(&mut self, source: &str) {
+        match Script::parse(source) {
+            Ok(script) => self.script = Some(script),
+            Err(e) => eprintln!("Failed to parse script: {:?}", e),
+        }
+    }
+
+    /// Runs commands until one needs to wait on the player, an npc, a timer, a fade or a battle
+    pub fn run_script(&mut self, renderer: &mut Renderer, dt: Duration) {
+        let fading = self.update_fade(dt);
+
+        loop {
+            let Some(script) = &mut self.script else {
+                return;
+            };
+
+            match &mut script.waiting_on {
+                Some(Wait::Npc(id)) => {
+                    let npc_id = (self.map.name.clone(), *id);
+                    let walking = self.npcs.iter()
+                        .find(|npc| npc.id == npc_id)
+                        .map_or(false, |npc| npc.position != npc.next_point);
+                    if walking {
+                        return;
+                    }
+                },
+                Some(Wait::Player { direction, steps }) => {
+                    if self.player.position != self.player.target_position {
+                        return;
+                    }
+                    if *steps > 0 {
+                        *steps -= 1;
+                        self.player.orient(*direction);
+                        self.player.set_direction(*direction, &self.map);
+                        //stop early when something is in the way
+                        if self.player.position != self.player.target_position {
+                            return;
+                        }
+                    }
+                },
+                Some(Wait::Timer(remaining)) => {
+                    if *remaining > dt {
+                        *remaining -= dt;
+                        return;
+                    }
+                },
+                Some(Wait::Camera) => {
+                    if self.camera.panning() {
+                        return;
+                    }
+                },
+                Some(Wait::Fade) => {
+                    if fading {
+                        return;
+                    }
+                },
+                None => {},
+            }
+            script.waiting_on = None;
+
+            let Some(command) = script.next_command() else {
+                self.script = None;
+                if !matches!(self.camera.target, CameraTarget::Player) {
+                    self.camera.pan_to(CameraTarget::Player);
+                }
+                return;
+            };
+
+            self.run_command(command, renderer);
+
+            if self.state != GameState::Running {
+                return;
+            }
+        }
+    }
+
+    fn run_command(&mut self, command: Command, renderer: &mut Renderer) {
+        println!("Script: {:?}", command);
+
+        match command {
+            Command::Text(text) => self.show_dialogue(&[&text], renderer),
+            Command::GiveItem { name, amount } => {
+                self.player.inventory.add(&name, amount);
+                let message = format!("You received {} {}.", amount, name);
+                self.show_dialogue(&[&message], renderer);
+            },
+            Command::SetFlag(flag) => {
+                self.flags.insert(flag);
+            },
+            Command::ClearFlag(flag) => {
+                self.flags.remove(&flag);
+            },
+            Command::If { flag, expected, command } => {
+                if self.flags.contains(&flag) == expected {
+                    self.run_command(*command, renderer);
+                }
+            },
+            Command::Walk { actor: Actor::Player, direction, steps } => {
+                if let Some(script) = &mut self.script {
+                    script.waiting_on = Some(Wait::Player { direction, steps });
+                }
+            },
+            Command::Walk { actor: Actor::Npc(id), direction, steps } => {
+                let npc_id = (self.map.name.clone(), id);
+                if let Some(npc) = self.npcs.iter_mut().find(|npc| npc.id == npc_id) {
+                    npc.direction = direction;
+                    npc.walk_to(npc.position + direction * steps as f32);
+                    if let Some(script) = &mut self.script {
+                        script.waiting_on = Some(Wait::Npc(id));
+                    }
+                }
+            },
+            Command::Camera(target) => {
+                self.camera.pan_to(target);
+                if let Some(script) = &mut self.script {
+                    script.waiting_on = Some(Wait::Camera);
+                }
+            },
+            Command::Face { actor: Actor::Player, direction } => self.player.orient(direction),
+            Command::Face { actor: Actor::Npc(id), direction } => {
+                let npc_id = (self.map.name.clone(), id);
+                if let Some(npc) = self.npcs.iter_mut().find(|npc| npc.id == npc_id) {
+                    npc.face(direction);
+                }
+            },
+            Command::Wait(duration) => {
+                if let Some(script) = &mut self.script {
+                    script.waiting_on = Some(Wait::Timer(duration));
+                }
+            },
+            Command::Fade { out } => {
+                self.begin_fade(out);
+                if let Some(script) = &mut self.script {
+                    script.waiting_on = Some(Wait::Fade);
+                }
+            },
+            Command::Cutscene(name) => {
+                let path = assets::get().path(&format!("{}.cutscene", name));
+                match (Script::load(&path.to_string_lossy()), &mut self.script) {
+                    (Ok(cutscene), Some(script)) => script.splice(cutscene),
+                    (Ok(cutscene), None) => self.script = Some(cutscene),
+                    (Err(e), _) => eprintln!("Failed to load cutscene: {:?}", e),
+                }
+            },
+            Command::Battle(id) => self.begin_transition(Destination::Battle((self.map.name.clone(), id))),
+            Command::Warp { map_name, location } => self.begin_transition(Destination::Warp { map_name, location }),
+            Command::Sound(name) => self.audio_player.play_sound(&assets::get().sound_path(&name).to_string_lossy()),
+            Command::Heal => self.heal_pokemon(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Command {
+        Command::parse(line).unwrap_or_else(|e| panic!("{} failed to parse: {:?}", line, e))
+    }
+
+    #[test]
+    fn parses_each_command() {
+        assert!(matches!(parse("text Welcome to the gym."), Command::Text(text) if text == "Welcome to the gym."));
+        assert!(matches!(parse("give 2 Poke Ball"), Command::GiveItem { name, amount: 2 } if name == "Poke Ball"));
+        assert!(matches!(parse("set talked_to_guard"), Command::SetFlag(flag) if flag == "talked_to_guard"));
+        assert!(matches!(parse("clear talked_to_guard"), Command::ClearFlag(flag) if flag == "talked_to_guard"));
+        assert!(matches!(parse("walk 12 up 3"), Command::Walk { actor: Actor::Npc(12), direction, steps: 3 } if direction == Vector3::new(0.0, 1.0, 0.0)));
+        assert!(matches!(parse("walk player left 1"), Command::Walk { actor: Actor::Player, direction, steps: 1 } if direction == Vector3::new(-1.0, 0.0, 0.0)));
+        assert!(matches!(parse("face player down"), Command::Face { actor: Actor::Player, direction } if direction == Vector3::new(0.0, -1.0, 0.0)));
+        assert!(matches!(parse("camera 12"), Command::Camera(CameraTarget::Npc(12))));
+        assert!(matches!(parse("camera player"), Command::Camera(CameraTarget::Player)));
+        // tiled counts rows downwards
+        assert!(matches!(parse("camera 20 14"), Command::Camera(CameraTarget::Position(position)) if position == Vector3::new(20.0, -14.0, 0.0)));
+        assert!(matches!(parse("wait 500"), Command::Wait(duration) if duration == Duration::from_millis(500)));
+        assert!(matches!(parse("fade out"), Command::Fade { out: true }));
+        assert!(matches!(parse("fade in"), Command::Fade { out: false }));
+        assert!(matches!(parse("cutscene gym_intro"), Command::Cutscene(name) if name == "gym_intro"));
+        assert!(matches!(parse("battle 12"), Command::Battle(12)));
+        assert!(matches!(parse("warp Route 1 2"), Command::Warp { map_name, location: 2 } if map_name == "Route 1"));
+        assert!(matches!(parse("sound Pokemon Gym.mp3"), Command::Sound(name) if name == "Pokemon Gym.mp3"));
+        assert!(matches!(parse("heal"), Command::Heal));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for line in [
+            "give 2",
+            "give two Potion",
+            "walk 12 up",
+            "walk 12 up 3 4",
+            "walk 12 sideways 3",
+            "walk someone up 3",
+            "face player",
+            "camera 20 north",
+            "wait",
+            "wait soon",
+            "fade",
+            "fade away",
+            "battle",
+            "warp gym",
+            "warp gym first",
+            "dance 3",
+        ] {
+            assert!(Command::parse(line).is_err(), "{} should not parse", line);
+        }
+    }
+
+    #[test]
+    fn nests_if_and_unless() {
+        let command = parse("if <Cascade Badge> unless beat_misty text Go challenge Misty.");
+
+        let Command::If { flag, expected: true, command } = command else {
+            panic!("expected an if");
+        };
+        assert_eq!(flag, "Cascade Badge");
+
+        let Command::If { flag, expected: false, command } = *command else {
+            panic!("expected an unless inside the if");
+        };
+        assert_eq!(flag, "beat_misty");
+        assert!(matches!(*command, Command::Text(text) if text == "Go challenge Misty."));
+    }
+
+    #[test]
+    fn rejects_bad_conditions() {
+        assert!(Command::parse("if talked_to_guard").is_err());
+        assert!(Command::parse("unless <Cascade Badge text Come back with a badge.").is_err());
+        // the command after the flag is checked too
+        assert!(Command::parse("if talked_to_guard walk 12 up").is_err());
+    }
+
+    #[test]
+    fn splits_scripts_on_lines_and_semicolons() {
+        let mut script = Script::parse("text Hello.\n\n  set met; heal  \n;").unwrap();
+
+        assert!(matches!(script.next_command(), Some(Command::Text(text)) if text == "Hello."));
+        assert!(matches!(script.next_command(), Some(Command::SetFlag(flag)) if flag == "met"));
+        assert!(matches!(script.next_command(), Some(Command::Heal)));
+        assert!(script.next_command().is_none());
+    }
+
+    #[test]
+    fn names_the_bad_line() {
+        let error = Script::parse("text Hello.\nwalk 12 up").err().unwrap();
+        assert!(error.to_string().contains("walk 12 up"), "{}", error);
+    }
+}
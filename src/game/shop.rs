@@ -0,0 +1,228 @@
+use winit::keyboard::KeyCode;
+use crate::game::font::Font;
+use crate::game::input_manager::InputManager;
+use crate::game::items::{Item, ItemEffect};
+use crate::game::player::Player;
+use crate::renderer::Renderer;
+use crate::renderer::sprite::Sprite;
+use crate::renderer::instance::Instance;
+
+const MAX_QUANTITY: u32 = 99;
+const ROWS_PER_PAGE: usize = 2;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ShopMode {
+    Buy,
+    Sell,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ShopState {
+    Main,
+    List,
+    Quantity,
+}
+
+pub struct Shop {
+    stock: Vec<Item>,
+    state: ShopState,
+    mode: ShopMode,
+    main_selection: usize, // buy, sell, quit
+    list_selection: usize, // last entry is cancel
+    quantity: u32,
+    message: Option<String>,
+    top_box: Sprite,
+    bottom_box: Sprite,
+    selector: Sprite,
+    text: Vec<Font>,
+}
+
+impl Shop {
+    pub fn new(stock: &[String], player: &Player, renderer: &mut Renderer) -> Self {
+        let top_box = renderer.create_sprite(0.0, 0.0, 0, 7, 15, 3, "battle", 1.0, 1.0).expect("");
+        let bottom_box = renderer.create_sprite(0.0, 7.0 * 16.0, 0, 7, 15, 3, "battle", 1.0, 1.0).expect("");
+        let selector = renderer.create_sprite(3.0, 119.0, 13, 10, 1, 1, "battle", 1.0, 1.0).expect("");
+
+        let mut shop = Self {
+            stock: stock.iter().map(|name| Item::new(name)).collect(),
+            state: ShopState::Main,
+            mode: ShopMode::Buy,
+            main_selection: 0,
+            list_selection: 0,
+            quantity: 1,
+            message: None,
+            top_box,
+            bottom_box,
+            selector,
+            text: Vec::new(),
+        };
+
+        shop.refresh(player, renderer);
+        shop
+    }
+
+    // (item, price, number the player can trade) for the current mode
+    fn entries(&self, player: &Player) -> Vec<(Item, u32, u32)> {
+        match self.mode {
+            // items without a price are never given away
+            ShopMode::Buy => self.stock.iter()
+                .filter(|item| item.price > 0)
+                .map(|item| (item.clone(), item.price, (player.money / item.price).min(MAX_QUANTITY)))
+                .collect(),
+            // key items and HMs can't be sold
+            ShopMode::Sell => player.inventory.items.iter()
+                .map(|(name, count)| (Item::new(name), *count))
+                .filter(|(item, _)| item.price > 0 && !matches!(item.effect, Some(ItemEffect::Teach(_))))
+                .map(|(item, count)| {
+                    let price = item.sell_price();
+                    (item, price, count)
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns true once the player leaves the shop
+    pub fn update(&mut self, input_manager: &mut InputManager, player: &mut Player, renderer: &mut Renderer) -> bool {
+        let Some(key) = input_manager.get_key_on_press() else {
+            return false;
+        };
+
+        self.message = None;
+        let entries = self.entries(player);
+
+        match self.state {
+            ShopState::Main => match key {
+                KeyCode::KeyA if self.main_selection > 0 => self.main_selection -= 1,
+                KeyCode::KeyD if self.main_selection < 2 => self.main_selection += 1,
+                KeyCode::KeyX => return true,
+                KeyCode::KeyZ => {
+                    match self.main_selection {
+                        0 => self.mode = ShopMode::Buy,
+                        1 => self.mode = ShopMode::Sell,
+                        _ => return true,
+                    }
+                    self.list_selection = 0;
+                    self.state = ShopState::List;
+                },
+                _ => {},
+            },
+            ShopState::List => match key {
+                KeyCode::KeyW if self.list_selection > 0 => self.list_selection -= 1,
+                KeyCode::KeyS if self.list_selection < entries.len() => self.list_selection += 1,
+                KeyCode::KeyX => self.state = ShopState::Main,
+                KeyCode::KeyZ => {
+                    match entries.get(self.list_selection) {
+                        None => self.state = ShopState::Main,
+                        Some((_, _, 0)) if self.mode == ShopMode::Buy => {
+                            self.message = Some("You do not have enough money.".to_string());
+                        },
+                        Some(_) => {
+                            self.quantity = 1;
+                            self.state = ShopState::Quantity;
+                        },
+                    }
+                },
+                _ => {},
+            },
+            ShopState::Quantity => {
+                let (item, price, max) = entries[self.list_selection].clone();
+
+                match key {
+                    KeyCode::KeyW => self.quantity = (self.quantity + 1).min(max),
+                    KeyCode::KeyS => self.quantity = self.quantity.saturating_sub(1).max(1),
+                    KeyCode::KeyD => self.quantity = (self.quantity + 10).min(max),
+                    KeyCode::KeyA => self.quantity = self.quantity.saturating_sub(10).max(1),
+                    KeyCode::KeyX => self.state = ShopState::List,
+                    KeyCode::KeyZ => {
+                        let total = price * self.quantity;
+
+                        match self.mode {
+                            ShopMode::Buy => {
+                                player.money -= total;
+                                player.inventory.add(&item.name, self.quantity);
+                                println!("Bought {} {} for {}", self.quantity, item.name, total);
+                                self.message = Some("Here you are. Thank you.".to_string());
+                            },
+                            ShopMode::Sell => {
+                                player.inventory.remove(&item.name, self.quantity);
+                                player.money += total;
+                                println!("Sold {} {} for {}", self.quantity, item.name, total);
+                                self.message = Some(format!("You got {} money.", total));
+                                self.list_selection = self.list_selection.min(self.entries(player).len());
+                            },
+                        }
+
+                        self.state = ShopState::List;
+                    },
+                    _ => {},
+                }
+            },
+        }
+
+        self.refresh(player, renderer);
+        false
+    }
+
+    fn refresh(&mut self, player: &Player, renderer: &mut Renderer) {
+        let entries = self.entries(player);
+        let mut text = Vec::new();
+
+        text.push(Font::new(14.0, 11.0, &format!("Money {}", player.money), true, "white_font", renderer));
+
+        let prompt = match (&self.message, self.state, self.mode) {
+            (Some(message), _, _) => message.clone(),
+            (None, ShopState::Main, _) => "Welcome. How may I help you?".to_string(),
+            (None, ShopState::List, ShopMode::Buy) => "What would you like?".to_string(),
+            (None, ShopState::List, ShopMode::Sell) if entries.is_empty() => "You have nothing to sell.".to_string(),
+            (None, ShopState::List, ShopMode::Sell) => "What will you sell?".to_string(),
+            (None, ShopState::Quantity, _) => format!("x {}", self.quantity),
+        };
+        text.push(Font::new(14.0, 26.0, &prompt, true, "white_font", renderer));
+
+        if self.state == ShopState::Quantity {
+            let total = entries[self.list_selection].1 * self.quantity;
+            text.push(Font::new(226.0, 26.0, &total.to_string(), false, "white_font", renderer));
+        }
+
+        match self.state {
+            ShopState::Main => {
+                for (i, option) in ["Buy", "Sell", "Quit"].iter().enumerate() {
+                    text.push(Font::new(14.0 + i as f32 * 72.0, 123.0, option, true, "white_font", renderer));
+                }
+                self.selector.update_position(3.0 + self.main_selection as f32 * 72.0, 119.0);
+            },
+            ShopState::List | ShopState::Quantity => {
+                let first_row = self.list_selection - self.list_selection % ROWS_PER_PAGE;
+
+                for row in 0..ROWS_PER_PAGE {
+                    let index = first_row + row;
+                    let y = 123.0 + row as f32 * 15.0;
+
+                    if let Some((item, price, _)) = entries.get(index) {
+                        text.push(Font::new(14.0, y, &item.name, true, "white_font", renderer));
+                        text.push(Font::new(226.0, y, &price.to_string(), false, "white_font", renderer));
+                    } else if index == entries.len() {
+                        text.push(Font::new(14.0, y, "Cancel", true, "white_font", renderer));
+                    }
+                }
+
+                let row = self.list_selection % ROWS_PER_PAGE;
+                self.selector.update_position(3.0, 119.0 + row as f32 * 15.0);
+            },
+        }
+
+        self.text = text;
+    }
+
+    // in screen space, drawn on the Ui layer of the overworld's draw list
+    pub fn instances(&self) -> Vec<Instance> {
+        let mut instances = Vec::new();
+        instances.extend_from_slice(&self.top_box.texture);
+        instances.extend_from_slice(&self.bottom_box.texture);
+        instances.extend_from_slice(&self.selector.texture);
+        for line in &self.text {
+            instances.extend_from_slice(&line.instanced());
+        }
+        instances
+    }
+}
@@ -0,0 +1,93 @@
+use cgmath::Vector3;
+use crate::game::map_loader::Terrain;
+
+// What is on a single tile. Doors, interactions, obstacles and triggers are indices into the map's lists of them
+#[derive(Clone, Default)]
+pub struct Cell {
+    pub solid: bool,
+    // surfable, blocked unless the player is surfing
+    pub water: bool,
+    pub grass: bool,
+    pub door: Option<usize>,
+    pub interaction: Option<usize>,
+    // trees and boulders, updated as they are cut down or pushed
+    pub obstacle: Option<usize>,
+    pub trigger: Option<usize>,
+    pub terrain: Option<Terrain>,
+    // map id of the npc standing on the tile or walking onto it
+    pub npc: Option<u32>,
+}
+
+static EMPTY: Cell = Cell {
+    solid: false,
+    water: false,
+    grass: false,
+    door: None,
+    interaction: None,
+    obstacle: None,
+    trigger: None,
+    terrain: None,
+    npc: None,
+};
+
+/// Tile occupancy for a map, indexed by integer tile coordinates.
+/// Tile (i, j) in Tiled sits at world position (i, -j)
+pub struct TileGrid {
+    pub width: u32,
+    pub height: u32,
+    cells: Vec<Cell>,
+}
+
+impl TileGrid {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::default(); (width * height) as usize],
+        }
+    }
+
+    // world positions are rounded to the tile they are closest to
+    fn index(&self, position: Vector3<f32>) -> Option<usize> {
+        let x = position.x.round() as i32;
+        let y = -position.y.round() as i32;
+
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+
+        Some((x + y * self.width as i32) as usize)
+    }
+
+    pub fn contains(&self, position: Vector3<f32>) -> bool {
+        self.index(position).is_some()
+    }
+
+    /// The tile at a world position, tiles outside the map are empty
+    pub fn cell(&self, position: Vector3<f32>) -> &Cell {
+        self.index(position).map_or(&EMPTY, |index| &self.cells[index])
+    }
+
+    /// None outside the map
+    pub fn cell_mut(&mut self, position: Vector3<f32>) -> Option<&mut Cell> {
+        self.index(position).map(|index| &mut self.cells[index])
+    }
+
+    pub fn clear_npcs(&mut self) {
+        for cell in &mut self.cells {
+            cell.npc = None;
+        }
+    }
+
+    pub fn clear_obstacles(&mut self) {
+        for cell in &mut self.cells {
+            cell.obstacle = None;
+        }
+    }
+
+    pub fn occupy(&mut self, position: Vector3<f32>, npc: u32) {
+        if let Some(cell) = self.cell_mut(position) {
+            cell.npc = Some(npc);
+        }
+    }
+}
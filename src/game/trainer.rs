@@ -0,0 +1,50 @@
+use crate::game::battle::BattleFormat;
+use crate::game::pokemon::Pokemon;
+use crate::renderer::Renderer;
+
+pub struct Trainer {
+    pub pokemon: Vec<Pokemon>,
+    pub format: BattleFormat,
+    // npc id of a trainer on the same map who joins in for a tag battle
+    pub partner: Option<u32>,
+    // paid out to the player on defeat
+    pub prize: u32,
+    // gym leaders hand over a badge when beaten
+    pub badge: Option<String>,
+}
+
+pub fn generate_trainer(map_name: &str, id: u32, renderer: &mut Renderer) -> Trainer {
+
+    let (team, format, partner) = match (map_name, id) {
+        ("gym", 5) => (vec![("Staryu", 18), ("Starmie", 21)], BattleFormat::Single, None),
+        ("gym", 4) => (vec![("Goldeen", 19), ("Psyduck", 18)], BattleFormat::Double, None),
+        // the swimmers team up, whichever of them spots the player
+        ("gym", 3) => (vec![("Horsea", 16), ("Shellder", 16)], BattleFormat::Single, Some(7)),
+        ("gym", 7) => (vec![("Tentacool", 16), ("Seel", 17)], BattleFormat::Single, Some(3)),
+        _ => (vec![("Charizard", 5)], BattleFormat::Single, None),
+    };
+
+    // base payout times the level of the trainer's last pokemon
+    let base_payout = match (map_name, id) {
+        ("gym", 5) => 99,
+        _ => 30,
+    };
+    let badge = match (map_name, id) {
+        ("gym", 5) => Some("Cascade".to_string()),
+        _ => None,
+    };
+
+    let prize = base_payout * team.last().map_or(0, |(_, level)| *level);
+
+    let pokemon = team.into_iter()
+        .map(|(name, level)| Pokemon::new(name.to_string(), level, renderer))
+        .collect();
+
+    Trainer {
+        pokemon,
+        format,
+        partner,
+        prize,
+        badge,
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+use winit::keyboard::KeyCode;
+use crate::game::font::Font;
+use crate::game::input_manager::InputManager;
+use crate::renderer::Renderer;
+use crate::renderer::sprite::Sprite;
+use crate::renderer::instance::Instance;
+
+pub struct TrainerCard {
+    top_box: Sprite,
+    bottom_box: Sprite,
+    text: Vec<Font>,
+}
+
+impl TrainerCard {
+    pub fn new(money: u32, play_time: Duration, seen: usize, caught: usize, badges: &[String], renderer: &mut Renderer) -> Self {
+        let top_box = renderer.create_sprite(0.0, 0.0, 0, 7, 15, 3, "battle", 1.0, 1.0).expect("");
+        let bottom_box = renderer.create_sprite(0.0, 7.0 * 16.0, 0, 7, 15, 3, "battle", 1.0, 1.0).expect("");
+
+        let minutes = play_time.as_secs() / 60;
+        let time = format!("Time {}h {:02}m", minutes / 60, minutes % 60);
+
+        let badge_list = if badges.is_empty() {
+            "Badges none".to_string()
+        } else {
+            format!("Badges {}", badges.join(", "))
+        };
+
+        let text = vec![
+            Font::new(14.0, 11.0, "Trainer Card", true, "white_font", renderer),
+            Font::new(14.0, 26.0, &format!("Money {}", money), true, "white_font", renderer),
+            Font::new(226.0, 26.0, &time, false, "white_font", renderer),
+            Font::new(14.0, 123.0, &format!("Caught {}", caught), true, "white_font", renderer),
+            Font::new(226.0, 123.0, &format!("Seen {}", seen), false, "white_font", renderer),
+            Font::new(14.0, 138.0, &badge_list, true, "white_font", renderer),
+        ];
+
+        Self {
+            top_box,
+            bottom_box,
+            text,
+        }
+    }
+
+    /// Returns true once the card is closed
+    pub fn update(&mut self, input_manager: &mut InputManager) -> bool {
+        matches!(input_manager.get_key_on_press(), Some(KeyCode::KeyZ | KeyCode::KeyX | KeyCode::KeyC))
+    }
+
+    // in screen space, drawn on the Ui layer of the overworld's draw list
+    pub fn instances(&self) -> Vec<Instance> {
+        let mut instances = Vec::new();
+        instances.extend_from_slice(&self.top_box.texture);
+        instances.extend_from_slice(&self.bottom_box.texture);
+        for line in &self.text {
+            instances.extend_from_slice(&line.instanced());
+        }
+        instances
+    }
+}
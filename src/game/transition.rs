@@ -0,0 +1,153 @@
+use std::time::Duration;
+use crate::game::{Game, GameState};
+use crate::game::pokemon::Pokemon;
+use crate::renderer::Renderer;
+use crate::renderer::transition::Wipe;
+
+const DOOR_DURATION: Duration = Duration::from_millis(200);
+const FADE_DURATION: Duration = Duration::from_millis(300);
+const BATTLE_INTRO_DURATION: Duration = Duration::from_millis(700);
+
+// what happens once the screen is fully covered
+pub enum Destination {
+    Door { map_name: String, location: u32 },
+    Warp { map_name: String, location: u32 },
+    Encounter(Pokemon),
+    Battle((String, u32)),
+}
+
+// The screen is covered by the wipe, the destination is entered, then the screen is uncovered.
+// Input is frozen the whole time since the game sits in GameState::Transition
+pub struct Transition {
+    pub wipe: Wipe,
+    // length of each half
+    duration: Duration,
+    timer: Duration,
+    // taken once the screen is covered
+    destination: Option<Destination>,
+    // drawn underneath, the state being left until the screen is covered and the new one after
+    pub scene: GameState,
+}
+
+impl Transition {
+    pub fn new(destination: Destination, scene: GameState) -> Self {
+        let (wipe, duration) = match destination {
+            Destination::Door { .. } => (Wipe::FadeBlack, DOOR_DURATION),
+            Destination::Warp { .. } => (Wipe::FadeWhite, FADE_DURATION),
+            Destination::Encounter(_) => (Wipe::Bars, BATTLE_INTRO_DURATION),
+            Destination::Battle(_) => (Wipe::Spiral, BATTLE_INTRO_DURATION),
+        };
+
+        Self {
+            wipe,
+            duration,
+            timer: Duration::ZERO,
+            destination: Some(destination),
+            scene,
+        }
+    }
+
+    /// How much of the screen is covered, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        let t = self.timer.as_secs_f32() / self.duration.as_secs_f32();
+        if self.destination.is_some() {
+            t
+        } else {
+            1.0 - t
+        }
+    }
+}
+
+// "fade out" and "fade in" in scripts, the overworld stays hidden once faded out
+pub struct Fade {
+    out: bool,
+    timer: Duration,
+}
+
+impl Fade {
+    pub fn new(out: bool) -> Self {
+        Self { out, timer: Duration::ZERO }
+    }
+
+    /// How much of the screen is covered, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        let t = (self.timer.as_secs_f32() / FADE_DURATION.as_secs_f32()).min(1.0);
+        if self.out {
+            t
+        } else {
+            1.0 - t
+        }
+    }
+}
+
+impl Game {
+    pub fn begin_fade(&mut self, out: bool) {
+        // the overworld shows through while fading back in
+        if !out {
+            self.faded = false;
+        }
+        self.fade = Some(Fade::new(out));
+    }
+
+    /// Returns whether the fade is still going
+    pub fn update_fade(&mut self, dt: Duration) -> bool {
+        let Some(fade) = &mut self.fade else {
+            return false;
+        };
+
+        fade.timer += dt;
+        if fade.timer < FADE_DURATION {
+            return true;
+        }
+
+        self.faded = fade.out;
+        self.fade = None;
+        false
+    }
+
+    pub fn begin_transition(&mut self, destination: Destination) {
+        self.transition = Some(Transition::new(destination, self.state));
+        self.state = GameState::Transition;
+    }
+
+    pub fn update_transition(&mut self, renderer: &mut Renderer, dt: Duration) {
+        self.update_camera(renderer, dt);
+
+        let Some(transition) = &mut self.transition else {
+            self.state = GameState::Running;
+            return;
+        };
+
+        transition.timer += dt;
+        if transition.timer < transition.duration {
+            return;
+        }
+        transition.timer = Duration::ZERO;
+
+        let Some(destination) = transition.destination.take() else {
+            // uncovered, carry on in the new scene with anything pressed meanwhile dropped
+            self.state = transition.scene;
+            self.transition = None;
+            self.input_manager.triggered_keys.clear();
+            return;
+        };
+
+        self.state = transition.scene;
+        match destination {
+            Destination::Door { map_name, location } | Destination::Warp { map_name, location } => {
+                self.load_map(&map_name, location, renderer);
+            },
+            Destination::Encounter(pokemon) => self.start_encounter(pokemon, renderer),
+            Destination::Battle(npc_id) => self.start_battle(npc_id, renderer),
+        }
+
+        if let Some(transition) = &mut self.transition {
+            transition.scene = self.state;
+            // battle screens fade in rather than unwinding the intro
+            if matches!(transition.wipe, Wipe::Bars | Wipe::Spiral) {
+                transition.wipe = Wipe::FadeBlack;
+            }
+        }
+        self.state = GameState::Transition;
+    }
+}
@@ -2,17 +2,30 @@ mod window;
 mod game;
 mod renderer;
 mod audio;
+mod options;
+mod capture;
+mod assets;
 
 use window::App;
+use options::Options;
 use winit::event_loop::EventLoop;
 
 #[tokio::main]
 async fn main() {
 
+    let options = Options::from_args();
+    println!("Seed: {}", options.seed);
+
+    let loaded = assets::find_root(options.assets_dir.as_deref()).and_then(assets::init);
+    if let Err(e) = loaded {
+        eprintln!("Failed to load assets: {:?}", e);
+        return;
+    }
+
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
 
-    let mut app = App::new();
+    let mut app = App::new(options);
 
     let _ = event_loop.run_app(&mut app);
 }
@@ -0,0 +1,76 @@
+use std::env;
+use std::path::PathBuf;
+use crate::capture::RecordFormat;
+
+pub struct Options {
+    pub seed: u64,
+    pub record_dir: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    // where screenshots and recordings are saved
+    pub capture_dir: PathBuf,
+    // how much a recording keeps
+    pub capture_seconds: u64,
+    pub capture_format: RecordFormat,
+    // asset folder given on the command line, see assets::find_root
+    pub assets_dir: Option<PathBuf>,
+}
+
+impl Options {
+    pub fn from_args() -> Self {
+        let mut seed = None;
+        let mut record_dir = None;
+        let mut replay = None;
+        let mut capture_dir = PathBuf::from("captures");
+        let mut capture_seconds = 10;
+        let mut capture_format = RecordFormat::Gif;
+        let mut assets_dir = None;
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    seed = args.next().and_then(|value| value.parse::<u64>().ok());
+                    if seed.is_none() {
+                        eprintln!("--seed expects an unsigned integer, using a random seed");
+                    }
+                }
+                "--record" => {
+                    record_dir = args.next().map(PathBuf::from);
+                }
+                "--replay" => {
+                    replay = args.next().map(PathBuf::from);
+                }
+                "--capture-dir" => {
+                    if let Some(dir) = args.next() {
+                        capture_dir = PathBuf::from(dir);
+                    }
+                }
+                "--capture-seconds" => {
+                    match args.next().and_then(|value| value.parse::<u64>().ok()) {
+                        Some(seconds) => capture_seconds = seconds,
+                        None => eprintln!("--capture-seconds expects an unsigned integer, keeping {}", capture_seconds),
+                    }
+                }
+                "--assets" => {
+                    assets_dir = args.next().map(PathBuf::from);
+                }
+                "--capture-png" => {
+                    capture_format = RecordFormat::PngSequence;
+                }
+                _ => {
+                    eprintln!("Unknown argument: {}", arg);
+                }
+            }
+        }
+
+        Self {
+            seed: seed.unwrap_or_else(rand::random),
+            record_dir,
+            replay,
+            capture_dir,
+            capture_seconds,
+            capture_format,
+            assets_dir,
+        }
+    }
+}
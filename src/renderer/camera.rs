@@ -1,4 +1,5 @@
 use cgmath::{ortho, Matrix4, Point3, Vector3};
+use crate::renderer::draw_list::Space;
 
 pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
     1.0, 0.0, 0.0, 0.0,
@@ -50,6 +51,15 @@ pub struct ConfigUniform {
     pub _padding: u32,
 }
 
+impl ConfigUniform {
+    pub fn new(space: Space) -> Self {
+        Self {
+            apply_camera: if space == Space::World { 1 } else { 0 },
+            _padding: 0,
+        }
+    }
+}
+
 impl CameraUniform {
     pub fn new() -> Self {
         use cgmath::SquareMatrix;
@@ -0,0 +1,118 @@
+use crate::renderer::instance::Instance;
+use crate::renderer::StaticBatch;
+
+/// Parts of a frame, drawn in this order
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Layer {
+    // tile layers under everything that moves
+    Map,
+    Entities,
+    // tiles drawn over the entities, like tree tops and roofs
+    Overlay,
+    Ui,
+}
+
+/// Whether a layer follows the camera or is laid out in screen pixels
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Space {
+    World,
+    Screen,
+}
+
+/// How the batches pushed to a layer are ordered before drawing
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Sort {
+    // in the order they were pushed
+    None,
+    // furthest up first, so anything lower down the screen is drawn over it
+    Y,
+    // lowest z first
+    Z,
+}
+
+pub(super) enum Batch<'a> {
+    Static(&'a StaticBatch),
+    // copied into the dynamic buffer each frame
+    Dynamic(Vec<Instance>),
+}
+
+struct Item<'a> {
+    batch: Batch<'a>,
+    // position used by Sort::Y and Sort::Z
+    key: f32,
+}
+
+struct LayerList<'a> {
+    space: Space,
+    sort: Sort,
+    items: Vec<Item<'a>>,
+}
+
+/// Everything to draw in a frame, grouped into layers. Map, entities and overlays follow the
+/// camera and UI is in screen space unless changed with set_space. Entities are sorted by y
+pub struct DrawList<'a> {
+    layers: [LayerList<'a>; 4],
+}
+
+impl<'a> DrawList<'a> {
+    pub fn new() -> Self {
+        let layer = |space, sort| LayerList { space, sort, items: Vec::new() };
+        Self {
+            layers: [
+                layer(Space::World, Sort::None),
+                layer(Space::World, Sort::Y),
+                layer(Space::World, Sort::None),
+                layer(Space::Screen, Sort::None),
+            ],
+        }
+    }
+
+    pub fn set_space(&mut self, layer: Layer, space: Space) {
+        self.layers[layer as usize].space = space;
+    }
+
+    pub fn set_sort(&mut self, layer: Layer, sort: Sort) {
+        self.layers[layer as usize].sort = sort;
+    }
+
+    /// Instances uploaded beforehand, such as a map's tile layers
+    pub fn push_static(&mut self, layer: Layer, batch: &'a StaticBatch) {
+        self.layers[layer as usize].items.push(Item { batch: Batch::Static(batch), key: 0.0 });
+    }
+
+    /// Instances drawn together. Sorted layers use the first instance's position
+    pub fn push(&mut self, layer: Layer, instances: &[Instance]) {
+        let key = match (self.layers[layer as usize].sort, instances.first()) {
+            (Sort::Y, Some(instance)) => instance.model[3][1],
+            (Sort::Z, Some(instance)) => instance.model[3][2],
+            _ => 0.0,
+        };
+        self.push_sorted(layer, key, instances);
+    }
+
+    /// Instances drawn together, ordered by the given y or z instead of their own position
+    pub fn push_sorted(&mut self, layer: Layer, key: f32, instances: &[Instance]) {
+        if instances.is_empty() {
+            return;
+        }
+        self.layers[layer as usize].items.push(Item { batch: Batch::Dynamic(instances.to_vec()), key });
+    }
+
+    /// The batches in drawing order along with the space each is drawn in
+    pub(super) fn into_batches(self) -> Vec<(Space, Batch<'a>)> {
+        let mut batches = Vec::new();
+
+        for mut list in self.layers {
+            match list.sort {
+                Sort::None => {},
+                // higher y is further up the screen
+                Sort::Y => list.items.sort_by(|a, b| b.key.total_cmp(&a.key)),
+                Sort::Z => list.items.sort_by(|a, b| a.key.total_cmp(&b.key)),
+            }
+
+            batches.extend(list.items.into_iter().map(|item| (list.space, item.batch)));
+        }
+
+        batches
+    }
+}
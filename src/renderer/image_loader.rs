@@ -3,7 +3,6 @@ use image::GenericImageView;
 use std::fs::{File, OpenOptions};
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
-use std::collections::HashMap;
 
 pub struct ImageData {
     pub width: u32,
@@ -64,15 +63,7 @@ fn load_or_cache_image(base_name: &str) -> Result<ImageData> {
     }
 }
 
-pub fn load_images(base_names: &[&str], assets_folder: &str) -> Result<HashMap<String, ImageData>> {
-    let mut images = HashMap::new();
-
-    for &base_name in base_names {
-        //concat the base name with the assets folder
-        let path = format!("{}/{}", assets_folder, base_name);
-        let image_data = load_or_cache_image(&path)?;
-        images.insert(base_name.to_string(), image_data);
-    }
-
-    Ok(images)
+/// Loads <base_path>.png
+pub fn load_image(base_path: &Path) -> Result<ImageData> {
+    load_or_cache_image(&base_path.to_string_lossy())
 }
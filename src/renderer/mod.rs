@@ -5,11 +5,15 @@ use wgpu::util::DeviceExt;
 use winit::window::Window;
 use camera::{Camera, CameraUniform, ConfigUniform};
 use instance::Instance;
-use pipeline::create_pipeline;
+use pipeline::{create_pipeline, create_wipe_pipeline};
 use texture_manager::{TextureManager, Atlas};
 use std::collections::HashMap;
 use sprite::Sprite;
 use window_map::WindowMapUniform;
+use transition::{Wipe, WipeUniform};
+use crate::assets;
+use draw_list::{Batch, DrawList, Layer, Space};
+use readback::Readback;
 
 pub mod camera;
 mod vertex;
@@ -20,31 +24,136 @@ pub mod texture_manager;
 pub mod image_loader;
 pub mod sprite;
 pub mod window_map;
+pub mod transition;
+pub mod draw_list;
+mod readback;
+
+const INITIAL_DYNAMIC_CAPACITY: usize = 4096;
+
+fn create_dynamic_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Dynamic Instance Buffer"),
+        size: (capacity * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+// headless frames are read back as tightly packed rgba
+const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+async fn request_device(adapter: &wgpu::Adapter) -> Result<(wgpu::Device, wgpu::Queue)> {
+    let limits = adapter.limits();
+    let max_texture_array_layers = limits.max_texture_array_layers;
+    println!("Max texture array layers: {}", max_texture_array_layers);
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: wgpu::Features::empty(),
+                // WebGL doesn't support all of wgpu's features, so if
+                // we're building for the web we'll have to disable some.
+                required_limits: if cfg!(target_arch = "wasm32") {
+                    wgpu::Limits::downlevel_webgl2_defaults()
+                } else {
+                    limits
+                },
+            },
+            None,
+        )
+        .await?;
+
+    Ok((device, queue))
+}
+
+fn create_offscreen_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: OFFSCREEN_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+// x, y, width and height of the 240 by 160 game area letterboxed into a target
+fn content_viewport(size: winit::dpi::PhysicalSize<u32>) -> (f32, f32, f32, f32) {
+    let (width, height) = (size.width as f32, size.height as f32);
+    let target_aspect = width / height;
+    let content_aspect = 240.0 / 160.0;
+
+    if target_aspect > content_aspect {
+        let new_width = height * content_aspect;
+        ((width - new_width) / 2.0, 0.0, new_width, height)
+    } else {
+        let new_height = width / content_aspect;
+        (0.0, (height - new_height) / 2.0, width, new_height)
+    }
+}
+
+// where frames end up
+enum Target {
+    Surface {
+        surface: wgpu::Surface<'static>,
+        config: wgpu::SurfaceConfiguration,
+    },
+    // no window, the frame stays in the texture until read back. Only the golden tests use it
+    #[cfg(test)]
+    Offscreen {
+        texture: wgpu::Texture,
+    },
+}
+
+/// Instances uploaded once and kept on the gpu, used for map tile layers
+#[derive(Default)]
+pub struct StaticBatch {
+    buffer: Option<wgpu::Buffer>,
+    count: u32,
+}
 
 pub struct Renderer {
-    surface: wgpu::Surface<'static>,
+    target: Target,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
     render_pipeline: wgpu::RenderPipeline,
+    offscreen_pipeline: wgpu::RenderPipeline,
+    // the wipe is drawn over each finished frame, once per target format like the sprites
+    wipe_pipeline: wgpu::RenderPipeline,
+    offscreen_wipe_pipeline: wgpu::RenderPipeline,
+    wipe_buffer: wgpu::Buffer,
+    wipe_bind_group: wgpu::BindGroup,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     num_indices: u32,
     pub camera: Camera,
     camera_uniform: CameraUniform,
     camera_buffer: wgpu::Buffer,
-    config_buffer: wgpu::Buffer,
-    camera_bind_group: wgpu::BindGroup,
-    instance_buffer: wgpu::Buffer,
-    num_instances: u32,
-    staging_buffer: wgpu::Buffer,
+    // one bind group per Space, switched between batches of a draw list. Their config never changes
+    world_bind_group: wgpu::BindGroup,
+    screen_bind_group: wgpu::BindGroup,
+    // instances that change every frame, reused and grown when a frame needs more room
+    dynamic_buffer: wgpu::Buffer,
+    dynamic_capacity: usize,
     pub texture_manager: TextureManager,
-    pub texture_map: HashMap<String, Atlas>,
     window_map_uniform: WindowMapUniform,
     window_map_buffer: wgpu::Buffer,
     window_map_bind_group: wgpu::BindGroup,
-    pub images: HashMap<String, image_loader::ImageData>,
+    // images read from disk so far, kept so a freed texture can be loaded again quickly
+    images: HashMap<String, image_loader::ImageData>,
+    wipe: Wipe,
+    wipe_progress: f32,
+    // set by capture_next_frame, the frame is drawn into a texture of its own and copied back
+    capture_size: Option<winit::dpi::PhysicalSize<u32>>,
+    readback: Readback,
 }
 
 impl Renderer {
@@ -71,27 +180,7 @@ impl Renderer {
             .await
             .unwrap();
 
-        let limits = adapter.limits();
-        let max_texture_array_layers = limits.max_texture_array_layers;
-        println!("Max texture array layers: {}", max_texture_array_layers);
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    required_features: wgpu::Features::empty(),
-                    // WebGL doesn't support all of wgpu's features, so if
-                    // we're building for the web we'll have to disable some.
-                    required_limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        limits
-                    },
-                },
-                None,
-            )
-            .await
-            .unwrap();
+        let (device, queue) = request_device(&adapter).await.unwrap();
 
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
@@ -113,25 +202,55 @@ impl Renderer {
 
         surface.configure(&device, &config);
 
-        let mut texture_manager = TextureManager::new(&device, &queue, 16, (1024, 8000));
-        let mut texture_map = HashMap::new();
+        Self::with_target(device, queue, Target::Surface { surface, config }, surface_format, size)
+    }
 
-        let mut add_texture = |name: &str, grid_w: u32, grid_h: u32, path: &str| {
-            texture_map.insert(name.to_string(), texture_manager.load_texture(path, grid_w, grid_h, &queue, &device).expect(""));
-        };
+    /// Renders into a texture instead of a window, read back with read_pixels. Without a gpu
+    /// this falls back to a software adapter such as lavapipe or llvmpipe
+    #[cfg(test)]
+    pub async fn new_headless(width: u32, height: u32) -> Result<Renderer> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+
+        let mut adapter = None;
+        for force_fallback_adapter in [false, true] {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: None,
+                    force_fallback_adapter,
+                })
+                .await;
+            if adapter.is_some() {
+                break;
+            }
+        }
+        let adapter = adapter.ok_or_else(|| anyhow!("No adapter available for headless rendering"))?;
+        println!("Headless adapter: {}", adapter.get_info().name);
 
-        add_texture("landing", 16, 16, "/home/chris/games/SirSquare/assets/landing.png");
-        add_texture("player", 16, 16, "/home/chris/games/SirSquare/assets/player.png");
-        add_texture("menu", 16, 16, "/home/chris/games/SirSquare/assets/menu.png");
-        add_texture("battle", 16, 16, "/home/chris/games/SirSquare/assets/battle.png");
-        add_texture("pokemon_back", 32, 32, "/home/chris/games/SirSquare/assets/pokemon_back.png");
-        add_texture("pokemon_front", 32, 32, "/home/chris/games/SirSquare/assets/pokemon_front.png");
-        add_texture("party", 16, 16, "/home/chris/games/SirSquare/assets/party.png");
-        add_texture("pokemon_party", 32, 32, "/home/chris/games/SirSquare/assets/pokemon_party.png");
-        add_texture("white_font", 7, 11, "/home/chris/games/SirSquare/assets/white_font.png");
-        add_texture("black_font", 7, 11, "/home/chris/games/SirSquare/assets/black_font.png");
-        add_texture("debug", 16, 16, "/home/chris/games/SirSquare/assets/debug.png");
-        add_texture("npcs", 16, 16, "/home/chris/games/SirSquare/assets/npcs.png");
+        let (device, queue) = request_device(&adapter).await?;
+
+        let texture = create_offscreen_texture(&device, width, height);
+
+        let size = winit::dpi::PhysicalSize::new(width, height);
+        Ok(Self::with_target(device, queue, Target::Offscreen { texture }, OFFSCREEN_FORMAT, size))
+    }
+
+    fn with_target(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        target: Target,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Renderer {
+        // every layer is as big as the largest texture, so size them from what the manifest lists
+        let texture_size = assets::get().largest_texture().unwrap_or_else(|e| {
+            println!("Failed to size the texture layers: {:?}", e);
+            (1024, 8000)
+        });
+        let texture_manager = TextureManager::new(&device, texture_size);
 
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
@@ -154,11 +273,6 @@ impl Renderer {
         let mut camera_uniform = CameraUniform::new();
         camera_uniform.update_view_proj(&camera);
 
-        let config_uniform = ConfigUniform {
-            apply_camera: 1, // 1 for true, 0 for false
-            _padding: 0,
-        };
-
         let camera_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Camera Buffer"),
@@ -167,11 +281,13 @@ impl Renderer {
             }
         );
 
-        let config_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Config Buffer"),
-            contents: bytemuck::cast_slice(&[config_uniform]),
+        let create_config_buffer = |label, space| device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&[ConfigUniform::new(space)]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
+        let world_config_buffer = create_config_buffer("World Config Buffer", Space::World);
+        let screen_config_buffer = create_config_buffer("Screen Config Buffer", Space::Screen);
 
         let camera_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[
@@ -199,7 +315,7 @@ impl Renderer {
             label: Some("camera_bind_group_layout"),
         });
 
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        let create_camera_bind_group = |label, config_buffer: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &camera_bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
@@ -211,8 +327,10 @@ impl Renderer {
                     resource: config_buffer.as_entire_binding(),
                 },
             ],
-            label: Some("camera_bind_group"),
+            label: Some(label),
         });
+        let world_bind_group = create_camera_bind_group("world_bind_group", &world_config_buffer);
+        let screen_bind_group = create_camera_bind_group("screen_bind_group", &screen_config_buffer);
 
 
         println!("Window size: {:?}", size);
@@ -263,10 +381,64 @@ impl Renderer {
         let render_pipeline = create_pipeline(
             &device,
             &shader,
-            &config,
+            format,
+            &render_pipeline_layout,
+        );
+        // captures are drawn into rgba textures whatever format the window uses
+        let offscreen_pipeline = create_pipeline(
+            &device,
+            &shader,
+            OFFSCREEN_FORMAT,
             &render_pipeline_layout,
         );
 
+        let wipe_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Wipe Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("wipe.wgsl").into()),
+        });
+
+        let wipe_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Wipe Buffer"),
+            contents: bytemuck::cast_slice(&[WipeUniform::new(Wipe::None, 0.0)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let wipe_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("wipe_bind_group_layout"),
+        });
+
+        let wipe_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &wipe_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wipe_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("wipe_bind_group"),
+        });
+
+        let wipe_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Wipe Pipeline Layout"),
+            bind_group_layouts: &[&wipe_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let wipe_pipeline = create_wipe_pipeline(&device, &wipe_shader, format, &wipe_pipeline_layout);
+        let offscreen_wipe_pipeline = create_wipe_pipeline(&device, &wipe_shader, OFFSCREEN_FORMAT, &wipe_pipeline_layout);
+
         let (vertices, indices) = vertex::get_quad();
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -284,70 +456,44 @@ impl Renderer {
         let num_indices = indices.len() as u32;
 
 
-        let mut instances : Vec<Instance> = Vec::new();
-
-        instances.push(Instance {
-            model: cgmath::Matrix4::from_translation(cgmath::Vector3::new(0.0, 0.0, 0.0)).into(),
-            tex_index: 1,
-            atlas_index: 0,
-        });
-
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Instance Buffer"),
-            contents: bytemuck::cast_slice(&instances),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC,
-        });
-
-        let num_instances = instances.len() as u32;
-
-        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Staging Buffer"),
-            size: (instances.len() * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
-            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let paths = vec![
-            "landing",
-            "pokecenter",
-            "pokemart",
-            "menu",
-            "house_1",
-            "battle",
-            "gym",
-        ];
-
-        let images = image_loader::load_images(&paths, "/home/chris/games/SirSquare/assets").unwrap();
+        let dynamic_capacity = INITIAL_DYNAMIC_CAPACITY;
+        let dynamic_buffer = create_dynamic_buffer(&device, dynamic_capacity);
 
         Self {
-            surface,
+            target,
             device,
             queue,
-            config,
             size,
             render_pipeline,
+            offscreen_pipeline,
+            wipe_pipeline,
+            offscreen_wipe_pipeline,
+            wipe_buffer,
+            wipe_bind_group,
             vertex_buffer,
             index_buffer,
             num_indices,
             camera,
             camera_uniform,
-            config_buffer,
             camera_buffer,
-            camera_bind_group,
-            instance_buffer,
-            num_instances,
-            staging_buffer,
+            world_bind_group,
+            screen_bind_group,
+            dynamic_buffer,
+            dynamic_capacity,
             texture_manager,
-            texture_map,
             window_map_uniform,
             window_map_buffer,
             window_map_bind_group,
-            images,
+            images: HashMap::new(),
+            wipe: Wipe::None,
+            wipe_progress: 0.0,
+            capture_size: None,
+            readback: Readback::new(),
         }
     }
 
     pub fn create_sprite(
-        &self,
+        &mut self,
         x: f32,
         y: f32,
         tex_x: u32,
@@ -358,8 +504,7 @@ impl Renderer {
         scale_x: f32,
         scale_y: f32,
     ) -> Result<Sprite> {
-        let atlas = self.texture_map.get(texture_name)
-            .ok_or_else(|| anyhow!("Texture name not found"))?;
+        let atlas = self.get_atlas(texture_name)?;
 
         Ok(Sprite::new(
             x,
@@ -378,30 +523,58 @@ impl Renderer {
         ))
     }
 
-    pub fn get_atlas(&self, texture_name: &str) -> Result<&Atlas> {
-        let atlas = self.texture_map.get(texture_name)
-            .ok_or_else(|| anyhow!("Texture name not found"))?;
+    /// The atlas for a texture, loading it first if it isn't on the gpu yet
+    pub fn get_atlas(&mut self, texture_name: &str) -> Result<&Atlas> {
+        if self.texture_manager.get(texture_name).is_none() {
+            self.load_texture(texture_name)?;
+        }
+
+        self.texture_manager.get(texture_name).ok_or_else(|| anyhow!("Texture name not found"))
+    }
+
+    /// Puts <name>.png from the asset folder into a free layer of the texture array, or reloads it in place
+    pub fn load_texture(&mut self, name: &str) -> Result<Atlas> {
+        if !self.images.contains_key(name) {
+            let image = image_loader::load_image(&assets::get().path(name))?;
+            self.images.insert(name.to_string(), image);
+        }
+
+        let (tile_width, tile_height) = assets::get().tile_size(name)?;
+        let image = &self.images[name];
+        let atlas = self.texture_manager.load(name, image, tile_width, tile_height, &self.queue, &self.device)?;
+        println!("Loaded texture {}: {}", name, atlas);
+
         Ok(atlas)
     }
 
-    pub fn update_texture(&mut self, atlas_index: u32, name: &str, grid_w: u32, grid_h: u32) -> Result<()> {
-        //remove previous texture at atlas index from texture map
+    /// Like get_atlas, and keeps the texture loaded until release_texture is called for it
+    pub fn hold_texture(&mut self, name: &str) -> Result<&Atlas> {
+        if self.texture_manager.get(name).is_none() {
+            self.load_texture(name)?;
+        }
+        self.texture_manager.hold(name);
 
-        let image = self.images.get(name).ok_or_else(|| anyhow!("Image not found"))?;
-        println!("Updating texture: {}", name);
-        let atlas = self.texture_manager.update_texture(atlas_index, image, grid_w, grid_h, &self.queue, &self.device).expect("");
-        println!("{}", atlas);
-        self.texture_map.insert(name.to_string(), atlas);
+        self.texture_manager.get(name).ok_or_else(|| anyhow!("Texture name not found"))
+    }
 
-        Ok(())
+    /// Frees the texture's layer for something else once nothing holds it, it is loaded again when next asked for
+    pub fn release_texture(&mut self, name: &str) {
+        self.texture_manager.release(name);
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            match &mut self.target {
+                Target::Surface { surface, config } => {
+                    config.width = new_size.width;
+                    config.height = new_size.height;
+                    surface.configure(&self.device, config);
+                },
+                // an offscreen texture keeps its size, the viewport letterboxes into it
+                #[cfg(test)]
+                Target::Offscreen { .. } => {},
+            }
         }
     }
 
@@ -412,61 +585,115 @@ impl Renderer {
         self.queue.write_buffer(&self.window_map_buffer, 0, bytemuck::cast_slice(&[self.window_map_uniform]));
     }
 
-    pub fn render(&mut self, dynamic_instances: &[Instance], use_cam: bool) -> Result<()> {
+    /// Covers the next frames with a wipe, progress goes from 0 (clear) to 1 (fully covered)
+    pub fn set_wipe(&mut self, wipe: Wipe, progress: f32) {
+        self.wipe = wipe;
+        self.wipe_progress = progress.clamp(0.0, 1.0);
+    }
 
-        let config_uniform = ConfigUniform {
-            apply_camera: if use_cam { 1 } else { 0 },
-            _padding: 0,
+    /// Copies the last frame drawn by a headless renderer back to memory as rgba rows
+    #[cfg(test)]
+    pub fn read_pixels(&mut self) -> Result<Vec<u8>> {
+        let Target::Offscreen { texture } = &self.target else {
+            bail!("Only a headless renderer can read back its frames");
         };
-        self.queue.write_buffer(&self.config_buffer, 0, bytemuck::cast_slice(&[config_uniform]));
-        // Combine static instances with additional dynamic instances (e.g., player)
-        {
-            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Copy Encoder"),
-            });
-            encoder.copy_buffer_to_buffer(
-                &self.instance_buffer,
-                0,
-                &self.staging_buffer,
-                0,
-                (self.num_instances * std::mem::size_of::<Instance>() as u32) as wgpu::BufferAddress,
-            );
-            self.queue.submit(std::iter::once(encoder.finish()));
-        }
 
-        // Wait for the copy to complete
-        let buffer_slice = self.staging_buffer.slice(..);
-        buffer_slice.map_async(wgpu::MapMode::Read, |result| {
-            if let Err(e) = result {
-                eprintln!("Failed to map buffer: {:?}", e);
-            }
+        // tests want the frame straight away, so this one waits on the gpu
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
         });
-
+        let id = self.readback.copy(&self.device, &mut encoder, texture);
+        self.queue.submit(iter::once(encoder.finish()));
+        self.readback.map_submitted();
         self.device.poll(wgpu::Maintain::Wait);
 
-        let data = buffer_slice.get_mapped_range();
-        let static_instances: &[Instance] = bytemuck::cast_slice(&data);
+        let (_, frame) = self.readback.collect(&self.device).into_iter()
+            .find(|(collected, _)| *collected == id)
+            .ok_or_else(|| anyhow!("Frame was not read back"))?;
+        Ok(frame?.into_raw())
+    }
+
+    /// Asks for the next frame to also be drawn at the given size, for screenshots and recordings.
+    /// Returns the id take_captures hands it back under a frame or more later, or None while every
+    /// staging buffer is still busy
+    pub fn capture_next_frame(&mut self, size: winit::dpi::PhysicalSize<u32>) -> Option<u64> {
+        if self.readback.is_full() || self.capture_size.is_some() {
+            return None;
+        }
+
+        self.capture_size = Some(size);
+        Some(self.readback.next_id())
+    }
+
+    /// Frames asked for with capture_next_frame that have made it back from the gpu, oldest first
+    pub fn take_captures(&mut self) -> Vec<(u64, Result<image::RgbaImage>)> {
+        self.readback.collect(&self.device)
+    }
 
-        // Combine static and dynamic instances
-        let mut combined_instances = Vec::with_capacity(static_instances.len() + dynamic_instances.len());
-        combined_instances.extend_from_slice(static_instances);
-        combined_instances.extend_from_slice(dynamic_instances);
+    /// Size of the letterboxed game area inside the window
+    pub fn content_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        let (_, _, width, height) = content_viewport(self.size);
+        winit::dpi::PhysicalSize::new(width.round() as u32, height.round() as u32)
+    }
 
-        // Unmap the staging buffer
-        drop(data);
-        self.staging_buffer.unmap();
+    pub fn create_static_batch(&self, instances: &[Instance]) -> StaticBatch {
+        if instances.is_empty() {
+            return StaticBatch::default();
+        }
 
-        // Create a new instance buffer with the combined instances
-        let instance_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Combined Instance Buffer"),
-            contents: bytemuck::cast_slice(&combined_instances),
+        let buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Static Instance Buffer"),
+            contents: bytemuck::cast_slice(instances),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        StaticBatch {
+            buffer: Some(buffer),
+            count: instances.len() as u32,
+        }
+    }
+
+    /// Draws one flat list of instances as UI, in screen pixels or following the camera
+    pub fn render(&mut self, instances: &[Instance], use_cam: bool) -> Result<()> {
+        let mut list = DrawList::new();
+        list.set_space(Layer::Ui, if use_cam { Space::World } else { Space::Screen });
+        list.push(Layer::Ui, instances);
+        self.draw(list)
+    }
+
+    pub fn draw(&mut self, list: DrawList) -> Result<()> {
+        self.queue.write_buffer(&self.wipe_buffer, 0, bytemuck::cast_slice(&[WipeUniform::new(self.wipe, self.wipe_progress)]));
+
+        let batches = list.into_batches();
+
+        // Pack every dynamic batch into the shared buffer, remembering where each one starts
+        let mut dynamic_instances = Vec::new();
+        let mut dynamic_ranges = Vec::new();
+        for (_, batch) in &batches {
+            if let Batch::Dynamic(instances) = batch {
+                let start = dynamic_instances.len() as u32;
+                dynamic_instances.extend_from_slice(instances);
+                dynamic_ranges.push(start..dynamic_instances.len() as u32);
+            }
+        }
+
+        if dynamic_instances.len() > self.dynamic_capacity {
+            self.dynamic_capacity = dynamic_instances.len().next_power_of_two();
+            self.dynamic_buffer = create_dynamic_buffer(&self.device, self.dynamic_capacity);
+        }
+        if !dynamic_instances.is_empty() {
+            self.queue.write_buffer(&self.dynamic_buffer, 0, bytemuck::cast_slice(&dynamic_instances));
+        }
+
+        let (view, output) = match &self.target {
+            Target::Surface { surface, .. } => {
+                let output = surface.get_current_texture()?;
+                let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+                (view, Some(output))
+            },
+            #[cfg(test)]
+            Target::Offscreen { texture } => (texture.create_view(&wgpu::TextureViewDescriptor::default()), None),
+        };
 
         let mut encoder = self
             .device
@@ -474,56 +701,93 @@ impl Renderer {
                 label: Some("Render Encoder"),
             });
 
+        self.encode_pass(&mut encoder, (&self.render_pipeline, &self.wipe_pipeline), &view, self.size, &batches, &dynamic_ranges);
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-
-            let window_aspect = self.size.width as f32 / self.size.height as f32;
-            let content_aspect = 240.0 / 160.0;
-
-            if window_aspect > content_aspect {
-                let new_width = self.size.height as f32 * content_aspect;
-                let x_offset = (self.size.width as f32 - new_width) / 2.0;
-                render_pass.set_viewport(x_offset, 0.0, new_width, self.size.height as f32, 0.0, 1.0);
-            } else {
-                let new_height = self.size.width as f32 / content_aspect;
-                let y_offset = (self.size.height as f32 - new_height) / 2.0;
-                render_pass.set_viewport(0.0, y_offset, self.size.width as f32, new_height, 0.0, 1.0);
-            }
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.texture_manager.bind_group, &[]);
-            render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-            render_pass.set_bind_group(2, &self.window_map_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..self.num_indices, 0, 0..combined_instances.len() as u32);
+        // drawn again into a texture of its own so it can be read back whatever the target is
+        if let Some(size) = self.capture_size.take() {
+            let texture = create_offscreen_texture(&self.device, size.width, size.height);
+            let capture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            self.encode_pass(&mut encoder, (&self.offscreen_pipeline, &self.offscreen_wipe_pipeline), &capture_view, size, &batches, &dynamic_ranges);
+            self.readback.copy(&self.device, &mut encoder, &texture);
         }
 
         self.queue.submit(iter::once(encoder.finish()));
-        output.present();
+        self.readback.map_submitted();
+        if let Some(output) = output {
+            output.present();
+        }
 
         Ok(())
     }
 
-}
+    fn encode_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        // sprite and wipe pipelines for the view's format
+        (pipeline, wipe_pipeline): (&wgpu::RenderPipeline, &wgpu::RenderPipeline),
+        view: &wgpu::TextureView,
+        size: winit::dpi::PhysicalSize<u32>,
+        batches: &[(Space, Batch)],
+        dynamic_ranges: &[std::ops::Range<u32>],
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.0,
+                        g: 0.0,
+                        b: 0.0,
+                        a: 1.0,
+                    }),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
 
+        let (x, y, width, height) = content_viewport(size);
+        render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &self.texture_manager.bind_group, &[]);
+        render_pass.set_bind_group(2, &self.window_map_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+        let mut dynamic_ranges = dynamic_ranges.iter().cloned();
+        for (space, batch) in batches {
+            let bind_group = match space {
+                Space::World => &self.world_bind_group,
+                Space::Screen => &self.screen_bind_group,
+            };
+            render_pass.set_bind_group(1, bind_group, &[]);
+
+            match batch {
+                Batch::Static(StaticBatch { buffer: Some(buffer), count }) => {
+                    render_pass.set_vertex_buffer(1, buffer.slice(..));
+                    render_pass.draw_indexed(0..self.num_indices, 0, 0..*count);
+                },
+                Batch::Static(_) => {},
+                Batch::Dynamic(_) => {
+                    let range = dynamic_ranges.next().unwrap_or(0..0);
+                    if !range.is_empty() {
+                        render_pass.set_vertex_buffer(1, self.dynamic_buffer.slice(..));
+                        render_pass.draw_indexed(0..self.num_indices, 0, range);
+                    }
+                },
+            }
+        }
+
+        if self.wipe != Wipe::None {
+            render_pass.set_pipeline(wipe_pipeline);
+            render_pass.set_bind_group(0, &self.wipe_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+
+}
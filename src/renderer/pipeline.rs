@@ -5,7 +5,7 @@ use super::Instance;
 pub fn create_pipeline(
     device: &Device,
     shader: &wgpu::ShaderModule,
-    config: &wgpu::SurfaceConfiguration,
+    format: wgpu::TextureFormat,
     render_pipeline_layout: &wgpu::PipelineLayout,
 ) -> wgpu::RenderPipeline {
 
@@ -22,7 +22,7 @@ pub fn create_pipeline(
             module: shader,
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
-                format: config.format,
+                format,
                 blend: Some(BlendState::ALPHA_BLENDING),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
@@ -46,3 +46,49 @@ pub fn create_pipeline(
         multiview: None,
     })
 }
+
+/// Draws the screen wipe over a finished frame, blended by how much of the screen it covers
+pub fn create_wipe_pipeline(
+    device: &Device,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    wipe_pipeline_layout: &wgpu::PipelineLayout,
+) -> wgpu::RenderPipeline {
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Wipe Pipeline"),
+        layout: Some(&wipe_pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use anyhow::*;
+
+// captures copied back at once, more are refused until one has been collected
+const STAGING_BUFFERS: usize = 3;
+
+struct InFlight {
+    id: u64,
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    // none until the copy has been submitted and the buffer asked to map
+    mapped: Option<Receiver<Result<(), wgpu::BufferAsyncError>>>,
+}
+
+/// Copies textures back to memory without stalling the gpu. Each copy goes into one of a few
+/// staging buffers that is mapped in the background and collected a frame or more later
+pub struct Readback {
+    next_id: u64,
+    in_flight: VecDeque<InFlight>,
+    // staging buffers already collected, reused by copies of the same size
+    free: Vec<wgpu::Buffer>,
+}
+
+impl Readback {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            in_flight: VecDeque::new(),
+            free: Vec::new(),
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.in_flight.len() >= STAGING_BUFFERS
+    }
+
+    /// Id the next copy will be collected under
+    pub fn next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    /// Records a copy of the texture into a staging buffer, map_submitted has to follow once the
+    /// encoder has been submitted
+    pub fn copy(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) -> u64 {
+        let (width, height) = (texture.width(), texture.height());
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (width * 4 + align - 1) / align * align;
+        let size = (padded_bytes_per_row * height) as wgpu::BufferAddress;
+
+        let buffer = match self.free.iter().position(|buffer| buffer.size() == size) {
+            Some(index) => self.free.swap_remove(index),
+            None => device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Readback Buffer"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+        };
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.in_flight.push_back(InFlight { id, buffer, width, height, padded_bytes_per_row, mapped: None });
+        id
+    }
+
+    /// Asks for the buffers of submitted copies to be mapped, they are ready once the gpu gets to them
+    pub fn map_submitted(&mut self) {
+        for copy in self.in_flight.iter_mut().filter(|copy| copy.mapped.is_none()) {
+            let (sender, receiver) = mpsc::channel();
+            copy.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+            copy.mapped = Some(receiver);
+        }
+    }
+
+    /// Copies that have finished mapping, oldest first. Doesn't wait on the gpu
+    pub fn collect(&mut self, device: &wgpu::Device) -> Vec<(u64, Result<image::RgbaImage>)> {
+        device.poll(wgpu::Maintain::Poll);
+
+        let mut frames = Vec::new();
+        while let Some(copy) = self.in_flight.front() {
+            let result = match copy.mapped.as_ref().map(|receiver| receiver.try_recv()) {
+                Some(std::result::Result::Ok(result)) => result.map_err(Error::from),
+                Some(Err(TryRecvError::Disconnected)) => Err(anyhow!("Readback buffer was dropped before it mapped")),
+                Some(Err(TryRecvError::Empty)) | None => break,
+            };
+
+            let copy = self.in_flight.pop_front().unwrap();
+            let frame = result.and_then(|_| {
+                let pixels = copy.pixels();
+                copy.buffer.unmap();
+                image::RgbaImage::from_raw(copy.width, copy.height, pixels)
+                    .ok_or_else(|| anyhow!("Captured frame has the wrong size"))
+            });
+            frames.push((copy.id, frame));
+
+            if self.free.len() < STAGING_BUFFERS {
+                self.free.push(copy.buffer);
+            }
+        }
+
+        frames
+    }
+}
+
+impl InFlight {
+    // rows are padded to the copy alignment, strip it off again
+    fn pixels(&self) -> Vec<u8> {
+        let unpadded_bytes_per_row = (self.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * self.height as usize);
+
+        let data = self.buffer.slice(..).get_mapped_range();
+        for row in data.chunks(self.padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+        }
+
+        pixels
+    }
+}
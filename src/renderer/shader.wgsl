@@ -41,7 +41,7 @@ struct TextureAtlas {
     _padding2: u32,
 }
 
-@group(0) @binding(1) var<uniform> atlas_infos: array<TextureAtlas, 16>;
+@group(0) @binding(1) var<uniform> atlas_infos: array<TextureAtlas, 64>;
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
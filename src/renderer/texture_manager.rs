@@ -1,9 +1,8 @@
 use anyhow::*;
-use image::GenericImageView;
 use wgpu::util::DeviceExt;
-use std::time::Instant;
 use super::image_loader::ImageData;
 use std::fmt;
+use std::collections::HashMap;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -36,36 +35,88 @@ impl fmt::Display for Atlas {
     }
 }
 
+// has to match the length of atlas_infos in shader.wgsl
+pub const MAX_ATLASES: u32 = 64;
+// layers the texture array starts with and adds whenever it fills up
+const INITIAL_LAYERS: u32 = 8;
+const GROW_LAYERS: u32 = 4;
+
+/// Textures share one texture array, one layer each. Layers are handed out by name as textures
+/// are loaded and the array grows when none are free. Maps hold their tileset while they are
+/// loaded and its layer is given back once the last of them releases it. Textures nothing
+/// holds, like sprites and fonts, stay loaded
 pub struct TextureManager {
     texture_array: wgpu::Texture,
     texture_array_view: wgpu::TextureView,
     sampler: wgpu::Sampler,
     pub bind_group: wgpu::BindGroup,
     pub bind_group_layout: wgpu::BindGroupLayout,
-    next_layer_index: u32,
+    // name of the texture in each layer, None when the layer is free
+    layers: Vec<Option<String>>,
+    atlases: HashMap<String, Atlas>,
+    // how many loaded maps use each texture
+    holders: HashMap<String, u32>,
+    max_layers: u32,
     atlas_infos: Vec<AtlasInfo>,
     atlas_info_buffer: wgpu::Buffer,
     texture_size: (u32, u32),
 }
 
-impl TextureManager {
-    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, max_textures: u32, texture_size: (u32, u32)) -> Self {
-        let texture_array = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Texture Array"),
-            size: wgpu::Extent3d {
-                width: texture_size.0,
-                height: texture_size.1,
-                depth_or_array_layers: max_textures,
+fn create_texture_array(device: &wgpu::Device, texture_size: (u32, u32), layers: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Texture Array"),
+        size: wgpu::Extent3d {
+            width: texture_size.0,
+            height: texture_size.1,
+            depth_or_array_layers: layers,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        // copied from when the array grows
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    atlas_info_buffer: &wgpu::Buffer,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
             },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: atlas_info_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+        label: Some("texture_array_bind_group"),
+    })
+}
+
+impl TextureManager {
+    pub fn new(device: &wgpu::Device, texture_size: (u32, u32)) -> Self {
+        let max_layers = MAX_ATLASES.min(device.limits().max_texture_array_layers);
+        let capacity = INITIAL_LAYERS.min(max_layers);
 
-        let texture_array_view = texture_array.create_view(&wgpu::TextureViewDescriptor::default());
+        let texture_array = create_texture_array(device, texture_size, capacity);
+        let texture_array_view = texture_array.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
 
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -108,18 +159,19 @@ impl TextureManager {
             label: Some("texture_array_bind_group_layout"),
         });
 
+        // the shader always sees MAX_ATLASES entries, only the ones in use are filled in
         let atlas_infos = vec![
             AtlasInfo {
                 atlas_width: 0,
                 atlas_height: 0,
-                tile_width: 16, // Fixed texture width
-                tile_height: 16, // Fixed texture height
+                tile_width: 16,
+                tile_height: 16,
                 texture_width: texture_size.0,
                 texture_height: texture_size.1,
                 _padding: 0,
                 _padding2: 0,
             };
-            max_textures as usize
+            MAX_ATLASES as usize
         ];
 
         let atlas_info_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -128,24 +180,7 @@ impl TextureManager {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_array_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: atlas_info_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-            label: Some("texture_array_bind_group"),
-        });
+        let bind_group = create_bind_group(device, &bind_group_layout, &texture_array_view, &atlas_info_buffer, &sampler);
 
         Self {
             texture_array,
@@ -153,22 +188,34 @@ impl TextureManager {
             sampler,
             bind_group,
             bind_group_layout,
-            next_layer_index: 0,
+            layers: vec![None; capacity as usize],
+            atlases: HashMap::new(),
+            holders: HashMap::new(),
+            max_layers,
             atlas_infos,
             atlas_info_buffer,
             texture_size,
         }
     }
 
-    pub fn load_texture(&mut self, texture_path: &str, tile_width: u32, tile_height: u32, queue: &wgpu::Queue, device: &wgpu::Device) -> Result<Atlas> {
-        let img = image::open(texture_path).context("Failed to open texture image")?;
-        let rgba = img.to_rgba8();
-        let dimensions = img.dimensions();
+    pub fn get(&self, name: &str) -> Option<&Atlas> {
+        self.atlases.get(name)
+    }
+
+    /// Uploads an image into a free layer under the given name. A name that is already loaded
+    /// keeps its layer and has its image replaced
+    pub fn load(&mut self, name: &str, image: &ImageData, tile_width: u32, tile_height: u32, queue: &wgpu::Queue, device: &wgpu::Device) -> Result<Atlas> {
+        let dimensions = (image.width, image.height);
+        if dimensions.0 > self.texture_size.0 || dimensions.1 > self.texture_size.1 {
+            bail!("{} is {}x{}, larger than a texture layer", name, dimensions.0, dimensions.1);
+        }
 
-        // Ensure we do not exceed the maximum number of textures in the array
-        assert!(self.next_layer_index < self.texture_array.size().depth_or_array_layers);
+        let layer_index = match self.atlases.get(name) {
+            Some(atlas) => atlas.index,
+            None => self.allocate_layer(device, queue)?,
+        };
+        self.layers[layer_index as usize] = Some(name.to_string());
 
-        // Upload the texture data to the texture array
         queue.write_texture(
             wgpu::ImageCopyTexture {
                 texture: &self.texture_array,
@@ -176,11 +223,11 @@ impl TextureManager {
                 origin: wgpu::Origin3d {
                     x: 0,
                     y: 0,
-                    z: self.next_layer_index,
+                    z: layer_index,
                 },
                 aspect: wgpu::TextureAspect::All,
             },
-            &rgba,
+            &image.data,
             wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * dimensions.0),
@@ -193,22 +240,17 @@ impl TextureManager {
             },
         );
 
-        // Update the atlas info map and buffer
         // account for texture size of the texture array
-        let atlas_info = AtlasInfo {
+        self.atlas_infos[layer_index as usize] = AtlasInfo {
             atlas_width: dimensions.0,
             atlas_height: dimensions.1,
-            tile_width, // Fixed texture width
-            tile_height, // Fixed texture height
+            tile_width,
+            tile_height,
             texture_width: self.texture_size.0,
             texture_height: self.texture_size.1,
             _padding: 0,
             _padding2: 0,
         };
-
-        self.atlas_infos[self.next_layer_index as usize] = atlas_info;
-
-        // Update the buffer with the new atlas info
         queue.write_buffer(&self.atlas_info_buffer, 0, bytemuck::cast_slice(&self.atlas_infos));
 
         let atlas = Atlas {
@@ -218,77 +260,73 @@ impl TextureManager {
             tile_height,
             texture_width: dimensions.0,
             texture_height: dimensions.1,
-            index: self.next_layer_index,
+            index: layer_index,
         };
+        self.atlases.insert(name.to_string(), atlas.clone());
 
-        self.next_layer_index += 1;
         Ok(atlas)
     }
 
-    pub fn update_texture(&mut self, layer_index: u32, image: &ImageData, tile_width: u32, tile_height: u32, queue: &wgpu::Queue, device: &wgpu::Device) -> Result<Atlas> {
+    /// Keeps a loaded texture from being freed until it is released as many times
+    pub fn hold(&mut self, name: &str) {
+        *self.holders.entry(name.to_string()).or_insert(0) += 1;
+    }
 
-        let rgba = &image.data;
-        let dimensions = (image.width, image.height);
-        println!("Dimensions: {:?}", dimensions);
+    /// Gives the texture's layer back to be reused once nothing holds it any more.
+    /// Textures that were never held are left alone
+    pub fn release(&mut self, name: &str) {
+        let Some(count) = self.holders.get_mut(name) else {
+            return;
+        };
 
-        // Ensure we do not exceed the maximum number of textures in the array
-        let start_time = Instant::now();
-        assert!(layer_index < self.texture_array.size().depth_or_array_layers);
-        println!("Assertion check time: {:?}", start_time.elapsed());
+        *count -= 1;
+        if *count > 0 {
+            return;
+        }
+        self.holders.remove(name);
 
-        // Update the texture in the texture array
-        let start_time = Instant::now();
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &self.texture_array,
-                mip_level: 0,
-                origin: wgpu::Origin3d {
-                    x: 0,
-                    y: 0,
-                    z: layer_index,
-                },
-                aspect: wgpu::TextureAspect::All,
-            },
-            &rgba,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * dimensions.0),
-                rows_per_image: Some(dimensions.1),
-            },
-            wgpu::Extent3d {
-                width: dimensions.0,
-                height: dimensions.1,
-                depth_or_array_layers: 1,
-            },
-        );
-        println!("Texture update time: {:?}", start_time.elapsed());
+        if let Some(atlas) = self.atlases.remove(name) {
+            println!("Freed texture {} from layer {}", name, atlas.index);
+            self.layers[atlas.index as usize] = None;
+        }
+    }
 
-        let start_time = Instant::now();
-        self.atlas_infos[layer_index as usize] = AtlasInfo {
-            atlas_width: dimensions.0,
-            atlas_height: dimensions.1,
-            tile_width: 16, // Fixed texture width
-            tile_height: 16, // Fixed texture height
-            texture_width: self.texture_size.0,
-            texture_height: self.texture_size.1,
-            _padding: 0,
-            _padding2: 0,
-        };
-        println!("Atlas info update time: {:?}", start_time.elapsed());
+    fn allocate_layer(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<u32> {
+        if let Some(index) = self.layers.iter().position(|layer| layer.is_none()) {
+            return Ok(index as u32);
+        }
 
-        let start_time = Instant::now();
-        queue.write_buffer(&self.atlas_info_buffer, 0, bytemuck::cast_slice(&self.atlas_infos));
-        println!("Buffer write time: {:?}", start_time.elapsed());
+        let capacity = self.layers.len() as u32;
+        if capacity >= self.max_layers {
+            bail!("All {} texture layers are in use", self.max_layers);
+        }
 
-        let atlas = Atlas {
-            cols: dimensions.0 / tile_width,
-            rows: dimensions.1 / tile_height,
-            tile_width,
-            tile_height,
-            texture_width: dimensions.0,
-            texture_height: dimensions.1,
-            index: layer_index,
-        };
-        Ok(atlas)
+        self.grow((capacity + GROW_LAYERS).min(self.max_layers), device, queue);
+        Ok(capacity)
+    }
+
+    // moves every layer into a bigger array, the bind group has to point at the new one
+    fn grow(&mut self, capacity: u32, device: &wgpu::Device, queue: &wgpu::Queue) {
+        println!("Growing texture array from {} to {} layers", self.layers.len(), capacity);
+
+        let texture_array = create_texture_array(device, self.texture_size, capacity);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Texture Array Grow Encoder"),
+        });
+        encoder.copy_texture_to_texture(
+            self.texture_array.as_image_copy(),
+            texture_array.as_image_copy(),
+            self.texture_array.size(),
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        self.texture_array_view = texture_array.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        self.texture_array = texture_array;
+        self.bind_group = create_bind_group(device, &self.bind_group_layout, &self.texture_array_view, &self.atlas_info_buffer, &self.sampler);
+        self.layers.resize(capacity as usize, None);
     }
 }
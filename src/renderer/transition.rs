@@ -0,0 +1,32 @@
+// Screen wipes drawn by wipe.wgsl as a full-screen overlay after everything else in a frame.
+// The numbers are what the shader matches on
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Wipe {
+    None = 0,
+    FadeBlack = 1,
+    FadeWhite = 2,
+    // horizontal bars closing in, before wild battles
+    Bars = 3,
+    // spiral closing in, before trainer battles
+    Spiral = 4,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct WipeUniform {
+    pub wipe: u32,
+    pub progress: f32,
+    pub _padding: u32,
+    pub _padding2: u32,
+}
+
+impl WipeUniform {
+    pub fn new(wipe: Wipe, progress: f32) -> Self {
+        Self {
+            wipe: wipe as u32,
+            progress,
+            _padding: 0,
+            _padding2: 0,
+        }
+    }
+}
@@ -0,0 +1,71 @@
+struct WipeUniform {
+    wipe: u32,
+    progress: f32,
+    _padding: u32,
+    _padding2: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> config: WipeUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // position on screen from 0,0 bottom left to 1,1 top right
+    @location(0) screen: vec2<f32>,
+}
+
+// one triangle big enough to cover the whole viewport, no vertex buffer needed
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+
+    let position = vec2<f32>(f32(index / 2u) * 4.0 - 1.0, f32(index % 2u) * 4.0 - 1.0);
+    out.clip_position = vec4<f32>(position, 0.0, 1.0);
+    out.screen = position * 0.5 + 0.5;
+
+    return out;
+}
+
+// how much of the wipe colour covers this point of the screen, from 0 to 1
+fn wipe_amount(screen: vec2<f32>) -> f32 {
+    let progress = config.progress;
+
+    switch config.wipe {
+        // fades
+        case 1u, 2u: {
+            return progress;
+        }
+        // bars sliding in from alternate sides
+        case 3u: {
+            let bar = u32(floor((1.0 - screen.y) * 8.0));
+            var x = screen.x;
+            if (bar % 2u == 1u) {
+                x = 1.0 - screen.x;
+            }
+            return select(0.0, 1.0, x < progress);
+        }
+        // sweeps round the screen, one ring at a time from the outside in
+        case 4u: {
+            let rings = 4.0;
+            let point = (screen - 0.5) * vec2<f32>(1.5, 1.0);
+            let angle = atan2(point.y, point.x) / 6.2831853 + 0.5;
+            let radius = length(point) / length(vec2<f32>(0.75, 0.5));
+            let ring = min(floor((1.0 - radius) * rings), rings - 1.0);
+            return select(0.0, 1.0, (ring + angle) / rings < progress);
+        }
+        default: {
+            return 0.0;
+        }
+    }
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var wipe_color = vec3<f32>(0.0, 0.0, 0.0);
+    if (config.wipe == 2u) {
+        wipe_color = vec3<f32>(1.0, 1.0, 1.0);
+    }
+
+    // blended over the finished frame, so a full wipe hides everything underneath
+    return vec4<f32>(wipe_color, wipe_amount(in.screen));
+}
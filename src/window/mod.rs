@@ -8,6 +8,8 @@ use winit::event::WindowEvent;
 use winit::event_loop::ActiveEventLoop;
 use winit::window::{Window, WindowId};
 use crate::renderer::Renderer;
+use crate::options::Options;
+use crate::capture::Capture;
 
 use std::time::{Duration, Instant};
 
@@ -19,10 +21,12 @@ pub struct App {
     frame_count: u32,
     fps_interval: Duration,
     surface_configured: bool,
+    options: Options,
+    capture: Capture,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(options: Options) -> Self {
         Self {
             window: None,
             game: None,
@@ -31,6 +35,8 @@ impl App {
             frame_count: 0,
             fps_interval: Duration::new(1, 0),
             surface_configured: false,
+            capture: Capture::new(&options),
+            options,
         }
     }
 }
@@ -46,7 +52,7 @@ impl ApplicationHandler for App {
         let renderer_future = Renderer::new(Arc::clone(&window));
         let mut renderer  = futures::executor::block_on(renderer_future);
 
-        let game_future = Game::new(&mut renderer);
+        let game_future = Game::new(&mut renderer, &self.options);
         let game = futures::executor::block_on(game_future);
 
 
@@ -62,6 +68,7 @@ impl ApplicationHandler for App {
                 event_loop.exit();
             },
             WindowEvent::KeyboardInput { event , .. } => {
+                self.capture.input(&event);
                 if let Some(game) = &mut self.game {
                     game.input(&event);
                 }
@@ -69,7 +76,13 @@ impl ApplicationHandler for App {
             WindowEvent::RedrawRequested => {
                 if let (Some(game), Some(renderer)) = (&mut self.game, &mut self.renderer) {
                     game.update(renderer);
+                    self.capture.before_draw(renderer);
                     game.draw(renderer);
+                    self.capture.after_draw(renderer);
+
+                    if game.quit {
+                        event_loop.exit();
+                    }
                 }
 
                 // Update frame count